@@ -0,0 +1,226 @@
+use std::fs::{read, read_to_string, write};
+use std::path::PathBuf;
+use std::process::exit;
+use std::str::FromStr;
+
+use hoyer_chip_8::{
+    database::RomDatabase,
+    display::{
+        palette::Palette,
+        screenshot::{to_ascii, Screenshot},
+    },
+    headless::{MachineState, Runner, Script, StopCondition, StopReason},
+    interpreter::{quirks::Quirks, timing::Timing},
+    vip::Vip,
+};
+
+const USAGE: &str = "Usage: chip8-headless <ROM> [OPTIONS]
+
+Run a CHIP-8 ROM without a window and dump the final state.
+
+Options:
+    --frames <N>             Number of 60 Hz frames to run [default: 600]
+    --tick-rate <N>          Instructions per frame [default: from database or 10]
+    --vip-timing             Charge each instruction its COSMAC VIP cost instead
+    --seed <N>               Seed the random number generator for repeatable runs
+    --quirks <PROFILE>       chip8, schip, modern or xochip [default: from database]
+    --until-pc <ADDR>        Stop when the program counter reaches a hex address
+    --until-loop             Stop when the program jumps to itself
+    --until-fault            Stop when the interpreter faults
+    --key <FRAME:KEY[:N]>    Hold hex KEY for N frames starting at FRAME
+    --script <FILE>          Read key presses from a file, one FRAME KEY [N] per line
+    --ascii <FILE>           Write the display as text, or - for stdout: # for
+                             the first plane, o for the second and @ for both
+    --image <FILE>           Write the display as a .png or .pbm image
+    --scale <N>              Size of each pixel in the image [default: 1]
+    --palette <COLOURS>      Image colours: a theme (classic, green, amber, lcd, octo)
+                             or #rrggbb,#rrggbb,... [default: from database or classic]
+    --state <FILE>           Write the registers as JSON, or - for stdout
+    --vip-monitor <FILE>     Emulate a COSMAC VIP with this monitor ROM image
+    --vip-interpreter <FILE> and this image of the original CHIP-8 interpreter";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let mut rom = None;
+    let mut frames = 600;
+    let mut timing = None;
+    let mut quirks = None;
+    let mut seed = None;
+    let mut stop_conditions = Vec::new();
+    let mut script = Script::default();
+    let mut ascii = None;
+    let mut image = None;
+    let mut screenshot = Screenshot::default();
+    let mut palette = None;
+    let mut state = None;
+    let mut vip_monitor = None;
+    let mut vip_interpreter = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .unwrap_or_else(|| fail(&format!("{} needs a value", arg)))
+        };
+        match arg.as_str() {
+            "--frames" => frames = parse_number(&value()),
+            "--tick-rate" => timing = Some(Timing::Instructions(parse_number(&value()))),
+            "--vip-timing" => timing = Some(Timing::CosmacVip),
+            "--seed" => seed = Some(parse_number(&value())),
+            "--quirks" => {
+                let profile = value();
+                quirks = Some(
+                    Quirks::profile(&profile)
+                        .unwrap_or_else(|| fail(&format!("unknown quirk profile {:?}", profile))),
+                )
+            }
+            "--until-pc" => {
+                let value = value();
+                let address = u16::from_str_radix(value.trim_start_matches("0x"), 16)
+                    .unwrap_or_else(|_| fail(&format!("invalid address {:?}", value)));
+                stop_conditions.push(StopCondition::ProgramCounter(address));
+            }
+            "--until-loop" => stop_conditions.push(StopCondition::Loop),
+            "--until-fault" => stop_conditions.push(StopCondition::Fault),
+            "--key" => script
+                .presses
+                .push(Script::parse_press(&value()).unwrap_or_else(|e| fail(&e))),
+            "--script" => {
+                let path = value();
+                let text =
+                    read_to_string(&path).unwrap_or_else(|e| fail(&format!("{}: {}", path, e)));
+                let file =
+                    Script::parse(&text).unwrap_or_else(|e| fail(&format!("{}: {}", path, e)));
+                script.presses.extend(file.presses);
+            }
+            "--ascii" => ascii = Some(value()),
+            "--image" => image = Some(PathBuf::from(value())),
+            "--scale" => screenshot.scale = parse_number::<u32>(&value()).max(1),
+            "--palette" => palette = Some(Palette::parse(&value()).unwrap_or_else(|e| fail(&e))),
+            "--state" => state = Some(value()),
+            "--vip-monitor" => vip_monitor = Some(value()),
+            "--vip-interpreter" => vip_interpreter = Some(value()),
+            _ if arg.starts_with("--") => fail(&format!("unknown option {}", arg)),
+            _ => rom = Some(arg),
+        }
+    }
+    let rom = rom.unwrap_or_else(|| fail("no ROM given"));
+    screenshot.palette = palette.unwrap_or_else(|| {
+        read(&rom)
+            .ok()
+            .and_then(|bytes| RomDatabase::builtin().lookup(&bytes))
+            .and_then(|info| info.palette())
+            .unwrap_or_default()
+    });
+
+    let vip = match (vip_monitor, vip_interpreter) {
+        (Some(monitor), Some(interpreter)) => Some(vip(&monitor, &interpreter, &rom)),
+        (None, None) => None,
+        _ => fail("--vip-monitor and --vip-interpreter go together"),
+    };
+    if let Some(mut vip) = vip {
+        if !stop_conditions.is_empty() || state.is_some() {
+            fail("--until-* and --state need the interpreter, not a VIP");
+        }
+        for frame in 0..frames {
+            script.apply(frame, vip.keypad());
+            vip.update_frame();
+        }
+        eprintln!("Ran {} frames", frames);
+        write_display([&vip.video_output(), &[0; 32]], ascii, image, &screenshot);
+        return;
+    }
+
+    let mut runner = Runner::from_rom(&rom).unwrap_or_else(|e| fail(&format!("{}: {}", rom, e)));
+    if let Some(quirks) = quirks {
+        runner.interpreter.quirks = quirks;
+    }
+    if let Some(timing) = timing {
+        runner.interpreter.timing = timing;
+    }
+    if let Some(seed) = seed {
+        runner.interpreter.seed(seed);
+    }
+    runner.script = script;
+    runner.stop_conditions = stop_conditions;
+
+    match runner.run(frames) {
+        StopReason::Frames => eprintln!("Ran {} frames", runner.frame),
+        StopReason::ProgramCounter(address) => {
+            eprintln!("Reached {:04X} after {} frames", address, runner.frame)
+        }
+        StopReason::Loop(address) => {
+            eprintln!("Looping at {:04X} after {} frames", address, runner.frame)
+        }
+        StopReason::Fault(fault) => eprintln!("Fault after {} frames: {}", runner.frame, fault),
+    }
+
+    let interpreter = &runner.interpreter;
+    if let Some(address) = interpreter.memory.reserved_write() {
+        eprintln!("Warning: wrote below the program area at {:03X}", address);
+    }
+    for modification in &interpreter.code_map.modifications {
+        eprintln!(
+            "Self-modifying code: {:03X} changed {:03X} from {:02X} to {:02X}",
+            modification.program_counter, modification.address, modification.old, modification.new
+        );
+    }
+    write_display(interpreter.planes(), ascii, image, &screenshot);
+    if let Some(path) = state {
+        let json = serde_json::to_string_pretty(&MachineState::new(interpreter)).unwrap();
+        output(&path, &(json + "\n"));
+    }
+    if interpreter.fault.is_some() {
+        exit(1);
+    }
+}
+
+fn write_display(
+    planes: [&[u64; 32]; 2],
+    ascii: Option<String>,
+    image: Option<PathBuf>,
+    screenshot: &Screenshot,
+) {
+    if let Some(path) = ascii {
+        output(&path, &to_ascii(planes));
+    }
+    if let Some(path) = image {
+        screenshot
+            .save(planes, &path)
+            .unwrap_or_else(|e| fail(&format!("{}: {}", path.display(), e)));
+    }
+}
+
+/// A VIP running the program in `rom`.
+fn vip(monitor: &str, interpreter: &str, rom: &str) -> Vip {
+    let read = |path: &str| read(path).unwrap_or_else(|e| fail(&format!("{}: {}", path, e)));
+    let mut vip =
+        Vip::new(&read(monitor), &read(interpreter)).unwrap_or_else(|e| fail(&e.to_string()));
+    vip.load_bytes(read(rom))
+        .unwrap_or_else(|e| fail(&format!("{}: {}", rom, e)));
+    vip
+}
+
+fn parse_number<T: FromStr>(value: &str) -> T {
+    value
+        .parse()
+        .unwrap_or_else(|_| fail(&format!("invalid number {:?}", value)))
+}
+
+fn output(path: &str, text: &str) {
+    if path == "-" {
+        print!("{}", text);
+    } else {
+        write(path, text).unwrap_or_else(|e| fail(&format!("{}: {}", path, e)));
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("chip8-headless: {}", message);
+    exit(2);
+}
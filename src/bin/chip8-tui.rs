@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::io::{stdout, Stdout, Write};
+use std::path::Path;
+use std::process::exit;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{poll, read, Event, KeyCode, KeyEvent, KeyModifiers},
+    execute, queue,
+    style::Print,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use hoyer_chip_8::{
+    constants::FRAME_DURATION,
+    database::RomDatabase,
+    display::terminal::{render, TextMode},
+    input::{
+        gamepad::Gamepads,
+        keymap::{KeyConfig, KeyMap},
+    },
+    interpreter::timing::Timing,
+    machine::Machine,
+    vip::Vip,
+};
+
+const USAGE: &str = "Usage: chip8-tui <ROM> [OPTIONS]
+
+Play a CHIP-8 ROM in the terminal.
+
+Options:
+    --braille        Draw with braille patterns instead of half blocks
+    --hold <MS>      How long a key stays down after the terminal reports it [default: 250]
+    --keys <FILE>    Key configuration [default: keys.json if there is one]
+    --layout <NAME>  qwerty, azerty, dvorak or numpad, instead of the configured layout
+    --vip-timing     Run at the speed of the original COSMAC VIP interpreter
+    --vip-monitor <FILE>
+    --vip-interpreter <FILE>
+                     Emulate a COSMAC VIP running the original interpreter, given
+                     images of its monitor ROM and of the CHIP-8 interpreter";
+
+/// Restores the terminal when dropped, even if we panic.
+struct RawTerminal(Stdout);
+
+impl RawTerminal {
+    fn new() -> crossterm::Result<RawTerminal> {
+        let mut stdout = stdout();
+        enable_raw_mode()?;
+        execute!(stdout, EnterAlternateScreen, Hide)?;
+        Ok(RawTerminal(stdout))
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = execute!(self.0, Show, LeaveAlternateScreen);
+        let _ = disable_raw_mode();
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let mut rom = None;
+    let mut mode = TextMode::HalfBlocks;
+    let mut hold = Duration::from_millis(250);
+    let mut vip_timing = false;
+    let mut vip_monitor = None;
+    let mut vip_interpreter = None;
+    let mut keys = None;
+    let mut layout = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .unwrap_or_else(|| fail(&format!("{} needs a value", arg)))
+        };
+        match arg.as_str() {
+            "--braille" => mode = TextMode::Braille,
+            "--vip-timing" => vip_timing = true,
+            "--vip-monitor" => vip_monitor = Some(value()),
+            "--vip-interpreter" => vip_interpreter = Some(value()),
+            "--keys" => keys = Some(value()),
+            "--layout" => layout = Some(value()),
+            "--hold" => {
+                let value = value();
+                hold = Duration::from_millis(
+                    value
+                        .parse()
+                        .unwrap_or_else(|_| fail(&format!("invalid hold time {:?}", value))),
+                );
+            }
+            option if option.starts_with('-') => {
+                fail(&format!("unknown option {}\n\n{}", option, USAGE))
+            }
+            _ if rom.is_some() => fail(&format!("more than one ROM given\n\n{}", USAGE)),
+            _ => rom = Some(arg),
+        }
+    }
+    let rom = rom.unwrap_or_else(|| fail(&format!("no ROM given\n\n{}", USAGE)));
+
+    let mut title = String::from("Hoyer's Chip-8 Interpreter");
+    let mut machine: Box<dyn Machine> = match (vip_monitor, vip_interpreter) {
+        (Some(monitor), Some(interpreter)) => {
+            title = String::from("COSMAC VIP");
+            Box::new(vip(&monitor, &interpreter, &rom))
+        }
+        (None, None) => {
+            let loaded = std::fs::read(&rom)
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| RomDatabase::builtin().load(bytes));
+            let (mut interpreter, _) = loaded.unwrap_or_else(|e| fail(&format!("{}: {}", rom, e)));
+            if vip_timing {
+                interpreter.timing = Timing::CosmacVip;
+            }
+            Box::new(interpreter)
+        }
+        _ => fail("--vip-monitor and --vip-interpreter go together"),
+    };
+    let mut actions = HashMap::new();
+    if let Some(info) = RomDatabase::builtin().lookup(machine.rom()) {
+        title = format!("{} - {}", info.title, title);
+        actions = info.keys.clone();
+    }
+
+    let config = match &keys {
+        Some(path) => KeyConfig::load(Path::new(path)),
+        None => KeyConfig::load_default(),
+    };
+    let mut config = config.unwrap_or_else(|e| fail(&e.to_string()));
+    if let Some(layout) = layout {
+        if KeyMap::layout(&layout).is_none() {
+            fail(&format!("unknown keyboard layout {:?}", layout));
+        }
+        config.layout = Some(layout);
+    }
+    let key_map = config.key_map(machine.rom());
+    let mut gamepads = Gamepads::for_actions(&config.gamepad_actions(machine.rom(), &actions));
+
+    let mut terminal = RawTerminal::new().unwrap();
+    run(
+        &mut terminal.0,
+        machine.as_mut(),
+        &title,
+        &key_map,
+        &mut gamepads,
+        mode,
+        hold,
+    )
+    .unwrap();
+}
+
+/// A VIP running the program in `rom`, exiting if any of the images are unusable.
+fn vip(monitor: &str, interpreter: &str, rom: &str) -> Vip {
+    let read =
+        |path: &str| std::fs::read(path).unwrap_or_else(|e| fail(&format!("{}: {}", path, e)));
+    let vip = Vip::new(&read(monitor), &read(interpreter)).and_then(|mut vip| {
+        vip.load_bytes(read(rom))?;
+        Ok(vip)
+    });
+    vip.unwrap_or_else(|e| fail(&e.to_string()))
+}
+
+fn run(
+    stdout: &mut Stdout,
+    machine: &mut dyn Machine,
+    title: &str,
+    key_map: &KeyMap,
+    gamepads: &mut Gamepads,
+    mode: TextMode,
+    hold: Duration,
+) -> crossterm::Result<()> {
+    // Terminals only report presses (and repeats), so keys are held until they go quiet
+    let mut last_pressed: [Option<Instant>; 16] = [None; 16];
+    let mut last_frame = None;
+    let mut waiting_for_key = false;
+    let mut time = Instant::now();
+
+    loop {
+        while poll(Duration::from_secs(0))? {
+            if let Event::Key(KeyEvent { code, modifiers }) = read()? {
+                match code {
+                    KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+                        return Ok(())
+                    }
+                    code => {
+                        if let Some(key) = key_name(code).and_then(|name| key_map.keypad_key(&name))
+                        {
+                            last_pressed[key as usize] = Some(Instant::now());
+                        }
+                    }
+                }
+            }
+        }
+        for (key, pressed) in last_pressed.iter().enumerate() {
+            machine.keypad()[key] = pressed.is_some_and(|time| time.elapsed() < hold);
+        }
+        gamepads.update(machine.keypad());
+
+        machine.update_frame();
+
+        let planes = machine.planes();
+        if last_frame != Some(planes) || waiting_for_key != machine.waiting_for_key() {
+            last_frame = Some(planes);
+            waiting_for_key = machine.waiting_for_key();
+            let status = if waiting_for_key {
+                " (waiting for input)"
+            } else {
+                "                    "
+            };
+            queue!(stdout, MoveTo(0, 0), Print(title), Print(status))?;
+            for (row, line) in render([&planes[0], &planes[1]], mode).iter().enumerate() {
+                queue!(stdout, MoveTo(0, row as u16 + 1), Print(line))?;
+            }
+            stdout.flush()?;
+        }
+
+        time += FRAME_DURATION;
+        if let Some(wait) = time.checked_duration_since(Instant::now()) {
+            sleep(wait);
+        }
+    }
+}
+
+/// The `KeyMap` name of a terminal key. Terminals can't tell numpad keys apart.
+fn key_name(code: KeyCode) -> Option<String> {
+    match code {
+        KeyCode::Char(' ') => Some(String::from("space")),
+        KeyCode::Char(c) => Some(c.to_lowercase().to_string()),
+        KeyCode::Up => Some(String::from("up")),
+        KeyCode::Down => Some(String::from("down")),
+        KeyCode::Left => Some(String::from("left")),
+        KeyCode::Right => Some(String::from("right")),
+        _ => None,
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("chip8-tui: {}", message);
+    exit(2);
+}
@@ -1,5 +1,9 @@
+use std::time::Duration;
+
 pub const WINDOW_WIDTH: u32 = 64;
 pub const WINDOW_HEIGHT: u32 = 32;
+pub const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
+pub const DEFAULT_TICK_RATE: u32 = 10;
 pub const FONTSET: [u8; 80] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
     0x20, 0x60, 0x20, 0x20, 0x70, // 1
@@ -0,0 +1,90 @@
+use std::fs::{read, read_dir, read_to_string};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::{RomDatabase, RomInfo};
+
+/// Where the bundled games are.
+pub const ROM_DIR: &str = "roms/games";
+
+/// File extensions of CHIP-8 programs and their descendants.
+pub const ROM_EXTENSIONS: [&str; 4] = ["ch8", "c8", "sc8", "xo8"];
+
+/// A ROM file found in a directory.
+#[derive(Clone, Debug)]
+pub struct RomEntry {
+    pub path: PathBuf,
+    /// What the database knows about it, if anything.
+    pub info: Option<RomInfo>,
+    /// A paragraph about it from a README, if there is one.
+    pub blurb: Option<String>,
+}
+
+impl RomEntry {
+    pub fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+    }
+
+    /// The title from the database, or the file name.
+    pub fn title(&self) -> String {
+        match &self.info {
+            Some(info) => info.title.clone(),
+            None => self.file_name(),
+        }
+    }
+}
+
+/// The ROMs in `dir`, sorted by file name, looked up in `database`.
+pub fn list_roms(dir: &Path, database: &RomDatabase) -> io::Result<Vec<RomEntry>> {
+    let mut roms = Vec::new();
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+        let is_rom = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                ROM_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+            });
+        if !is_rom || !path.is_file() {
+            continue;
+        }
+        let info = read(&path).ok().and_then(|rom| database.lookup(&rom));
+        let blurb = blurb(&path);
+        roms.push(RomEntry { path, info, blurb });
+    }
+    roms.sort_by_key(|rom| rom.file_name().to_lowercase());
+    Ok(roms)
+}
+
+/// The first paragraph of a text file next to the ROM with the same name,
+/// such as `PONG.txt` or `PONG.md`, or of the section of the directory's
+/// `README.md` whose heading names the ROM.
+pub fn blurb(rom: &Path) -> Option<String> {
+    let stem = rom.file_stem()?.to_string_lossy().to_lowercase();
+    for extension in ["txt", "md"].iter() {
+        if let Ok(text) = read_to_string(rom.with_extension(extension)) {
+            return first_paragraph(text.lines());
+        }
+    }
+    let readme = read_to_string(rom.with_file_name("README.md")).ok()?;
+    let mut lines = readme.lines();
+    lines.find(|line| line.starts_with('#') && line.to_lowercase().contains(&stem))?;
+    first_paragraph(lines.take_while(|line| !line.starts_with('#')))
+}
+
+/// The first run of non-blank lines, joined with spaces, skipping headings.
+fn first_paragraph<'a>(lines: impl Iterator<Item = &'a str>) -> Option<String> {
+    let paragraph: Vec<&str> = lines
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .skip_while(|line| line.is_empty())
+        .take_while(|line| !line.is_empty())
+        .collect();
+    if paragraph.is_empty() {
+        None
+    } else {
+        Some(paragraph.join(" "))
+    }
+}
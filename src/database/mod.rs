@@ -0,0 +1,176 @@
+pub mod library;
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use sha1::Sha1;
+
+use crate::display::palette::{parse_color, Palette};
+use crate::interpreter::interpreter::Interpreter;
+use crate::interpreter::memory::{self, MEMORY_SIZE};
+use crate::interpreter::quirks::Quirks;
+use crate::interpreter::timing::Timing;
+
+/// Database of known ROMs keyed by SHA-1 hash.
+///
+/// Reads the `programs.json` format of the community
+/// [chip-8-database](https://github.com/chip-8/chip-8-database).
+pub struct RomDatabase {
+    programs: Vec<Program>,
+    hashes: HashMap<String, (usize, String)>,
+}
+
+/// Everything the database knows about a single ROM.
+#[derive(Clone, Debug, Default)]
+pub struct RomInfo {
+    pub title: String,
+    pub description: Option<String>,
+    pub authors: Vec<String>,
+    pub release: Option<String>,
+    pub file: Option<String>,
+    pub platform: Option<String>,
+    /// Recommended number of instructions to execute per 60 Hz frame.
+    pub tick_rate: Option<u32>,
+    pub quirks: Option<Quirks>,
+    /// Named actions such as `up` or `player2Down` mapped to keypad keys.
+    pub keys: HashMap<String, u8>,
+    /// Pixel colours as RGB, background first.
+    pub colors: Vec<[u8; 3]>,
+}
+
+impl RomInfo {
+    /// The ROM's colours as a palette, if it has enough of them.
+    pub fn palette(&self) -> Option<Palette> {
+        Palette::new(self.colors.clone())
+    }
+
+    /// How much memory the ROM's platform has.
+    pub fn memory_size(&self) -> usize {
+        self.platform
+            .as_deref()
+            .map_or(MEMORY_SIZE, memory::size_for_platform)
+    }
+
+    /// Set the interpreter up the way the ROM wants: its quirks and speed.
+    /// The memory size has to be right before the ROM is loaded, so
+    /// `RomDatabase::load` picks that.
+    pub fn configure(&self, interpreter: &mut Interpreter) {
+        if let Some(quirks) = self.quirks {
+            interpreter.quirks = quirks;
+        }
+        if let Some(rate) = self.tick_rate {
+            interpreter.timing = Timing::Instructions(rate);
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Program {
+    title: String,
+    description: Option<String>,
+    #[serde(default)]
+    authors: Vec<String>,
+    release: Option<String>,
+    roms: HashMap<String, Rom>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Rom {
+    file: Option<String>,
+    #[serde(default)]
+    platforms: Vec<String>,
+    tickrate: Option<u32>,
+    #[serde(default)]
+    quirky_platforms: HashMap<String, HashMap<String, bool>>,
+    #[serde(default)]
+    keys: HashMap<String, u8>,
+    colors: Option<Colors>,
+}
+
+#[derive(Deserialize)]
+struct Colors {
+    #[serde(default)]
+    pixels: Vec<String>,
+}
+
+impl RomDatabase {
+    /// The database shipped with the interpreter, covering the bundled ROMs.
+    pub fn builtin() -> RomDatabase {
+        RomDatabase::from_json(include_str!("programs.json")).unwrap()
+    }
+
+    /// Parse a chip-8-database `programs.json` file.
+    pub fn from_json(json: &str) -> serde_json::Result<RomDatabase> {
+        let programs: Vec<Program> = serde_json::from_str(json)?;
+        let mut hashes = HashMap::new();
+        for (i, program) in programs.iter().enumerate() {
+            for hash in program.roms.keys() {
+                hashes.insert(hash.to_lowercase(), (i, hash.clone()));
+            }
+        }
+        Ok(RomDatabase { programs, hashes })
+    }
+
+    /// Load a ROM into an interpreter with as much memory as its platform
+    /// has, configured the way the database says if it knows the ROM. Also
+    /// returns what it knows. Fails if the ROM doesn't fit in the memory.
+    pub fn load(&self, bytes: Vec<u8>) -> Result<(Interpreter, Option<RomInfo>), String> {
+        let info = self.lookup(&bytes);
+        let size = info.as_ref().map_or(MEMORY_SIZE, RomInfo::memory_size);
+        let mut interpreter = Interpreter::from_bytes(bytes, size)?;
+        if let Some(info) = &info {
+            info.configure(&mut interpreter);
+        }
+        Ok((interpreter, info))
+    }
+
+    /// Look up a ROM by its contents.
+    pub fn lookup(&self, rom: &[u8]) -> Option<RomInfo> {
+        self.lookup_hash(&sha1_hex(rom))
+    }
+
+    /// Look up a ROM by its hex encoded SHA-1 hash.
+    pub fn lookup_hash(&self, hash: &str) -> Option<RomInfo> {
+        let (i, key) = self.hashes.get(&hash.to_lowercase())?;
+        let program = &self.programs[*i];
+        let rom = &program.roms[key];
+
+        let platform = rom.platforms.first().cloned();
+        let quirks = platform.as_ref().and_then(|platform| {
+            let mut quirks = Quirks::for_platform(platform)?;
+            if let Some(overrides) = rom.quirky_platforms.get(platform) {
+                for (name, value) in overrides {
+                    quirks.set(name, *value);
+                }
+            }
+            Some(quirks)
+        });
+        let colors = match &rom.colors {
+            Some(colors) => colors
+                .pixels
+                .iter()
+                .filter_map(|c| parse_color(c))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        Some(RomInfo {
+            title: program.title.clone(),
+            description: program.description.clone(),
+            authors: program.authors.clone(),
+            release: program.release.clone(),
+            file: rom.file.clone(),
+            platform,
+            tick_rate: rom.tickrate,
+            quirks,
+            keys: rom.keys.clone(),
+            colors,
+        })
+    }
+}
+
+/// Hex encoded SHA-1 hash of a ROM.
+pub fn sha1_hex(rom: &[u8]) -> String {
+    Sha1::from(rom).digest().to_string()
+}
@@ -0,0 +1,83 @@
+// CRT effects over the display texture. Effects::shade in effects.rs does the
+// same on the CPU, so change both together. The overlay goes on top, flat.
+
+[[builtin(vertex_index)]] var<in> in_vertex_index: u32;
+[[builtin(position)]] var<out> out_position: vec4<f32>;
+[[location(0)]] var<out> out_uv: vec2<f32>;
+
+// One triangle covering the viewport
+[[stage(vertex)]]
+fn vs_main() {
+    const x: f32 = f32(i32(in_vertex_index) / 2) * 4.0 - 1.0;
+    const y: f32 = f32(i32(in_vertex_index) & 1) * 4.0 - 1.0;
+    out_uv = vec2<f32>(x * 0.5 + 0.5, 0.5 - y * 0.5);
+    out_position = vec4<f32>(x, y, 0.0, 1.0);
+}
+
+[[block]]
+struct Params {
+    // Scanlines, bloom, curvature and pixel grid strength
+    effects: vec4<f32>;
+    // Texture width and height in xy
+    size: vec4<f32>;
+};
+
+[[group(0), binding(0)]] var r_texture: texture_2d<f32>;
+[[group(0), binding(1)]] var r_sampler: sampler;
+[[group(0), binding(2)]] var<uniform> r_params: Params;
+[[group(0), binding(3)]] var r_overlay: texture_2d<f32>;
+
+[[location(0)]] var<in> in_uv: vec2<f32>;
+[[location(0)]] var<out> out_color: vec4<f32>;
+
+fn texel(cell: vec2<f32>) -> vec3<f32> {
+    const last: vec2<f32> = r_params.size.xy - vec2<f32>(1.0, 1.0);
+    const clamped: vec2<f32> = clamp(cell, vec2<f32>(0.0, 0.0), last);
+    return textureSample(r_texture, r_sampler, (clamped + vec2<f32>(0.5, 0.5)) / r_params.size.xy).xyz;
+}
+
+fn blur(cell: vec2<f32>) -> vec3<f32> {
+    const sum: vec3<f32> = texel(cell + vec2<f32>(-1.0, -1.0))
+        + texel(cell + vec2<f32>(0.0, -1.0))
+        + texel(cell + vec2<f32>(1.0, -1.0))
+        + texel(cell + vec2<f32>(-1.0, 0.0))
+        + texel(cell)
+        + texel(cell + vec2<f32>(1.0, 0.0))
+        + texel(cell + vec2<f32>(-1.0, 1.0))
+        + texel(cell + vec2<f32>(0.0, 1.0))
+        + texel(cell + vec2<f32>(1.0, 1.0));
+    return sum / 9.0;
+}
+
+[[stage(fragment)]]
+fn fs_main() {
+    const scanlines: f32 = r_params.effects.x;
+    const bloom: f32 = r_params.effects.y;
+    const curvature: f32 = r_params.effects.z;
+    const pixel_grid: f32 = r_params.effects.w;
+    const overlay: vec4<f32> = textureSample(r_overlay, r_sampler, in_uv);
+
+    // Bulge the picture out from the centre
+    const centred: vec2<f32> = in_uv * 2.0 - vec2<f32>(1.0, 1.0);
+    const bent: vec2<f32> = centred + centred * centred.yx * centred.yx * curvature;
+    const uv: vec2<f32> = bent * 0.5 + vec2<f32>(0.5, 0.5);
+    if (uv.x < 0.0 || uv.y < 0.0 || uv.x >= 1.0 || uv.y >= 1.0) {
+        out_color = vec4<f32>(overlay.xyz * overlay.w, 1.0);
+        return;
+    }
+
+    const position: vec2<f32> = uv * r_params.size.xy;
+    const cell: vec2<f32> = floor(position);
+    const inside: vec2<f32> = position - cell;
+    var color: vec3<f32> = texel(cell) + blur(cell) * bloom;
+
+    // Darken the edges of each pixel, then the gaps between rows
+    const edge: f32 = min(min(inside.x, 1.0 - inside.x), min(inside.y, 1.0 - inside.y));
+    if (edge < 0.1) {
+        color = color * (1.0 - pixel_grid);
+    }
+    color = color * (1.0 - scanlines * (1.0 - sin(inside.y * 3.14159265)));
+
+    color = clamp(color, vec3<f32>(0.0, 0.0, 0.0), vec3<f32>(1.0, 1.0, 1.0));
+    out_color = vec4<f32>(mix(color, overlay.xyz, vec3<f32>(overlay.w, overlay.w, overlay.w)), 1.0);
+}
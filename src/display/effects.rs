@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+
+/// How many window pixels each CHIP-8 pixel gets when effects are drawn on
+/// the CPU.
+pub const CPU_SCALE: u32 = 8;
+
+/// Which renderer draws the effects.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Renderer {
+    /// A shader on the graphics card.
+    #[default]
+    Gpu,
+    /// `Effects::apply` into a larger frame, for software renderers.
+    Cpu,
+}
+
+/// CRT effects drawn over the display, each from 0 (off) to 1.
+///
+/// `src/display/crt.wgsl` draws these on the GPU and `apply` on the CPU, the
+/// same way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Effects {
+    /// Dark gaps between rows of pixels.
+    pub scanlines: f32,
+    /// Light bleeding out of lit pixels onto their neighbours.
+    pub bloom: f32,
+    /// How far the picture bulges out like the glass of a tube.
+    pub curvature: f32,
+    /// Dark borders around every pixel, like an LCD.
+    pub pixel_grid: f32,
+}
+
+impl Effects {
+    /// A bit of everything a CRT does.
+    pub fn crt() -> Effects {
+        Effects {
+            scanlines: 0.5,
+            bloom: 0.3,
+            curvature: 0.1,
+            pixel_grid: 0.0,
+        }
+    }
+
+    pub fn is_off(&self) -> bool {
+        *self == Effects::default()
+    }
+
+    /// The strengths in the order the shader takes them.
+    pub fn to_uniform(self) -> [f32; 4] {
+        [self.scanlines, self.bloom, self.curvature, self.pixel_grid]
+    }
+
+    /// Draw the RGBA image `source` of `source_size` stretched over `target`
+    /// of `target_size` with the effects.
+    pub fn apply(
+        &self,
+        source: &[u8],
+        source_size: (u32, u32),
+        target: &mut [u8],
+        target_size: (u32, u32),
+    ) {
+        let (width, height) = (source_size.0 as i32, source_size.1 as i32);
+        let texel = |x: i32, y: i32| {
+            let i = (y.clamp(0, height - 1) * width + x.clamp(0, width - 1)) as usize * 4;
+            [
+                source[i] as f32 / 255.0,
+                source[i + 1] as f32 / 255.0,
+                source[i + 2] as f32 / 255.0,
+            ]
+        };
+        for (i, pixel) in target.chunks_exact_mut(4).enumerate() {
+            let x = (i as u32 % target_size.0) as f32 + 0.5;
+            let y = (i as u32 / target_size.0) as f32 + 0.5;
+            let uv = [x / target_size.0 as f32, y / target_size.1 as f32];
+            let color = self.shade(uv, [width as f32, height as f32], texel);
+            for (channel, value) in pixel.iter_mut().zip(color.iter()) {
+                *channel = (value * 255.0).round() as u8;
+            }
+            pixel[3] = 0xff;
+        }
+    }
+
+    /// The colour at `uv` across the screen, from 0 to 1 each way, as
+    /// `fs_main` in the shader works it out.
+    fn shade(
+        &self,
+        uv: [f32; 2],
+        size: [f32; 2],
+        texel: impl Fn(i32, i32) -> [f32; 3],
+    ) -> [f32; 3] {
+        // Bulge the picture out from the centre
+        let centred = [uv[0] * 2.0 - 1.0, uv[1] * 2.0 - 1.0];
+        let bend = |a: f32, b: f32| a + a * b * b * self.curvature;
+        let uv = [
+            bend(centred[0], centred[1]) * 0.5 + 0.5,
+            bend(centred[1], centred[0]) * 0.5 + 0.5,
+        ];
+        if uv.iter().any(|value| !(0.0..1.0).contains(value)) {
+            return [0.0; 3];
+        }
+
+        let position = [uv[0] * size[0], uv[1] * size[1]];
+        let cell = [position[0].floor(), position[1].floor()];
+        let inside = [position[0] - cell[0], position[1] - cell[1]];
+        let (x, y) = (cell[0] as i32, cell[1] as i32);
+        let mut color = texel(x, y);
+        if self.bloom > 0.0 {
+            let mut blur = [0.0; 3];
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    for (sum, value) in blur.iter_mut().zip(texel(x + dx, y + dy).iter()) {
+                        *sum += value / 9.0;
+                    }
+                }
+            }
+            for (channel, value) in color.iter_mut().zip(blur.iter()) {
+                *channel += value * self.bloom;
+            }
+        }
+
+        // Darken the edges of each pixel, then the gaps between rows
+        let edge = inside[0]
+            .min(1.0 - inside[0])
+            .min(inside[1].min(1.0 - inside[1]));
+        let mut brightness =
+            1.0 - self.scanlines * (1.0 - (inside[1] * std::f32::consts::PI).sin());
+        if edge < 0.1 {
+            brightness *= 1.0 - self.pixel_grid;
+        }
+        color.map(|channel| (channel * brightness).clamp(0.0, 1.0))
+    }
+}
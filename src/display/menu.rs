@@ -0,0 +1,356 @@
+use std::path::PathBuf;
+
+use crate::constants::DEFAULT_TICK_RATE;
+use crate::database::library::RomEntry;
+use crate::interpreter::quirks::{Quirks, PROFILES};
+use crate::interpreter::snapshot::SAVE_SLOTS;
+use crate::interpreter::timing::Timing;
+
+/// Instructions per frame the speed setting steps through.
+pub const SPEEDS: [u32; 12] = [5, 7, 10, 12, 15, 20, 30, 50, 100, 200, 500, 1000];
+
+/// Characters that fit across the overlay.
+const COLUMNS: usize = 63;
+/// ROMs listed at once in the browser.
+const LIST_ROWS: usize = 13;
+/// The browser's list of file names, with the details to its right.
+const LIST_WIDTH: usize = 15;
+
+/// The keys the menu understands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuKey {
+    Up,
+    Down,
+    Left,
+    Right,
+    Select,
+    Back,
+}
+
+/// Something the menu wants the frontend to do.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MenuAction {
+    /// The menu closed, so carry on.
+    Resume,
+    Load(PathBuf),
+    Reset,
+    Timing(Timing),
+    Quirks(Quirks),
+    SaveState(u8),
+    LoadState(u8),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Item {
+    Resume,
+    Browse,
+    Reset,
+    Speed,
+    Quirks,
+    Slot,
+    Save,
+    Load,
+}
+
+const ITEMS: [Item; 8] = [
+    Item::Resume,
+    Item::Browse,
+    Item::Reset,
+    Item::Speed,
+    Item::Quirks,
+    Item::Slot,
+    Item::Save,
+    Item::Load,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Page {
+    Main,
+    Browser,
+}
+
+/// The in-window menu: a page of settings and actions, and a browser for
+/// the ROMs in a directory.
+///
+/// Frontends feed it keys with `press`, carry out the actions it returns and
+/// show `lines` on the overlay while it's open.
+pub struct Menu {
+    open: bool,
+    page: Page,
+    item: usize,
+    /// The ROMs the browser lists, from `library::list_roms`.
+    pub roms: Vec<RomEntry>,
+    /// Where the ROMs came from, for the browser's heading.
+    pub rom_dir: String,
+    rom: usize,
+    /// The settings shown, which `open` takes from the interpreter.
+    pub timing: Timing,
+    pub quirks: Quirks,
+    /// The save state slot to save to and load from.
+    pub slot: u8,
+}
+
+impl Menu {
+    pub fn new(roms: Vec<RomEntry>, rom_dir: &str) -> Menu {
+        Menu {
+            open: false,
+            page: Page::Main,
+            item: 0,
+            roms,
+            rom_dir: rom_dir.to_string(),
+            rom: 0,
+            timing: Timing::default(),
+            quirks: Quirks::default(),
+            slot: 1,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Open the main page, showing the interpreter's `timing` and `quirks`.
+    pub fn open(&mut self, timing: Timing, quirks: Quirks) {
+        self.open = true;
+        self.page = Page::Main;
+        self.item = 0;
+        self.timing = timing;
+        self.quirks = quirks;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    /// Handle a key, returning what the frontend should do about it.
+    pub fn press(&mut self, key: MenuKey) -> Option<MenuAction> {
+        if !self.open {
+            return None;
+        }
+        match self.page {
+            Page::Main => self.press_main(key),
+            Page::Browser => self.press_browser(key),
+        }
+    }
+
+    fn press_main(&mut self, key: MenuKey) -> Option<MenuAction> {
+        let item = ITEMS[self.item];
+        match key {
+            MenuKey::Up => self.item = (self.item + ITEMS.len() - 1) % ITEMS.len(),
+            MenuKey::Down => self.item = (self.item + 1) % ITEMS.len(),
+            MenuKey::Back => return self.close_with(MenuAction::Resume),
+            MenuKey::Left | MenuKey::Right | MenuKey::Select => {
+                let up = key != MenuKey::Left;
+                match item {
+                    Item::Speed => {
+                        self.timing = step_speed(self.timing, up);
+                        return Some(MenuAction::Timing(self.timing));
+                    }
+                    Item::Quirks => {
+                        self.quirks = step_quirks(self.quirks, up);
+                        return Some(MenuAction::Quirks(self.quirks));
+                    }
+                    Item::Slot => {
+                        self.slot = if up {
+                            self.slot % SAVE_SLOTS + 1
+                        } else {
+                            (self.slot + SAVE_SLOTS - 2) % SAVE_SLOTS + 1
+                        };
+                    }
+                    _ if key != MenuKey::Select => {}
+                    Item::Resume => return self.close_with(MenuAction::Resume),
+                    Item::Browse => self.page = Page::Browser,
+                    Item::Reset => return self.close_with(MenuAction::Reset),
+                    Item::Save => return self.close_with(MenuAction::SaveState(self.slot)),
+                    Item::Load => return self.close_with(MenuAction::LoadState(self.slot)),
+                }
+            }
+        }
+        None
+    }
+
+    fn press_browser(&mut self, key: MenuKey) -> Option<MenuAction> {
+        let last = self.roms.len().saturating_sub(1);
+        match key {
+            MenuKey::Up if self.rom == 0 => self.rom = last,
+            MenuKey::Up => self.rom -= 1,
+            MenuKey::Down if self.rom >= last => self.rom = 0,
+            MenuKey::Down => self.rom += 1,
+            MenuKey::Left => self.rom = self.rom.saturating_sub(LIST_ROWS),
+            MenuKey::Right => self.rom = (self.rom + LIST_ROWS).min(last),
+            MenuKey::Back => self.page = Page::Main,
+            MenuKey::Select => {
+                if let Some(rom) = self.roms.get(self.rom) {
+                    let path = rom.path.clone();
+                    return self.close_with(MenuAction::Load(path));
+                }
+            }
+        }
+        None
+    }
+
+    fn close_with(&mut self, action: MenuAction) -> Option<MenuAction> {
+        self.close();
+        Some(action)
+    }
+
+    /// The text of the open page, every line the same width. Empty when the
+    /// menu is closed.
+    pub fn lines(&self) -> Vec<String> {
+        if !self.open {
+            return Vec::new();
+        }
+        let lines = match self.page {
+            Page::Main => self.main_lines(),
+            Page::Browser => self.browser_lines(),
+        };
+        let width = lines.iter().map(|line| line.chars().count()).max();
+        lines
+            .into_iter()
+            .map(|line| format!("{:1$}", line, width.unwrap_or(0)))
+            .collect()
+    }
+
+    fn main_lines(&self) -> Vec<String> {
+        let mut lines = vec![String::from(" Menu"), String::new()];
+        for (i, item) in ITEMS.iter().enumerate() {
+            let text = match item {
+                Item::Resume => String::from("Resume"),
+                Item::Browse => String::from("Load ROM..."),
+                Item::Reset => String::from("Reset"),
+                Item::Speed => format!("Speed   < {} >", speed_name(self.timing)),
+                Item::Quirks => {
+                    let name = self.quirks.profile_name().unwrap_or("custom");
+                    format!("Quirks  < {} >", name)
+                }
+                Item::Slot => format!("Slot    < {} >", self.slot),
+                Item::Save => format!("Save state to slot {}", self.slot),
+                Item::Load => format!("Load state from slot {}", self.slot),
+            };
+            let marker = if i == self.item { '>' } else { ' ' };
+            lines.push(format!("{} {} ", marker, text));
+        }
+        lines.push(String::new());
+        lines.push(String::from(" Enter picks, Esc closes "));
+        lines
+    }
+
+    fn browser_lines(&self) -> Vec<String> {
+        let mut lines = vec![format!(" Load ROM from {}", self.rom_dir), String::new()];
+        if self.roms.is_empty() {
+            lines.push(String::from(" No ROMs here"));
+        }
+        let top = (self.rom + 1).saturating_sub(LIST_ROWS);
+        let details = self.roms.get(self.rom).map(details).unwrap_or_default();
+        for row in 0..LIST_ROWS.min(self.roms.len()) {
+            let i = top + row;
+            let marker = if i == self.rom { '>' } else { ' ' };
+            let name: String = self.roms[i]
+                .file_name()
+                .chars()
+                .take(LIST_WIDTH - 3)
+                .collect();
+            let detail = details.get(row).map_or("", String::as_str);
+            let line = format!("{} {:2$} {3}", marker, name, LIST_WIDTH - 2, detail);
+            lines.push(line.trim_end().to_string());
+        }
+        lines.push(String::new());
+        lines.push(String::from(" Enter loads, Esc goes back"));
+        lines
+    }
+}
+
+/// Step to the next speed in `SPEEDS` up or down. The COSMAC VIP's timing
+/// steps from `DEFAULT_TICK_RATE`.
+fn step_speed(timing: Timing, up: bool) -> Timing {
+    let rate = match timing {
+        Timing::Instructions(rate) => rate,
+        Timing::CosmacVip => DEFAULT_TICK_RATE,
+    };
+    let next = if up {
+        SPEEDS.iter().find(|speed| **speed > rate)
+    } else {
+        SPEEDS.iter().rev().find(|speed| **speed < rate)
+    };
+    Timing::Instructions(*next.unwrap_or(&rate))
+}
+
+fn speed_name(timing: Timing) -> String {
+    match timing {
+        Timing::Instructions(rate) => format!("{} per frame", rate),
+        Timing::CosmacVip => String::from("COSMAC VIP"),
+    }
+}
+
+/// Step to the next profile in `PROFILES`, or the first or last from quirks
+/// that aren't a profile.
+fn step_quirks(quirks: Quirks, up: bool) -> Quirks {
+    let current = PROFILES
+        .iter()
+        .position(|name| quirks.profile_name() == Some(name));
+    let next = match (current, up) {
+        (Some(i), true) => (i + 1) % PROFILES.len(),
+        (Some(i), false) => (i + PROFILES.len() - 1) % PROFILES.len(),
+        (None, true) => 0,
+        (None, false) => PROFILES.len() - 1,
+    };
+    Quirks::profile(PROFILES[next]).unwrap_or_default()
+}
+
+/// What the browser shows about a ROM, wrapped to fit beside the list.
+fn details(rom: &RomEntry) -> Vec<String> {
+    let mut lines = vec![rom.title()];
+    match &rom.info {
+        Some(info) => {
+            let mut credit = info.authors.join(", ");
+            if let Some(release) = &info.release {
+                credit = format!("{} ({})", credit, release).trim().to_string();
+            }
+            if !credit.is_empty() {
+                lines.push(credit);
+            }
+            let mut platform = info.platform.clone().unwrap_or_default();
+            if let Some(rate) = info.tick_rate {
+                platform = format!("{}, {} per frame", platform, rate);
+            }
+            lines.push(platform.trim_start_matches(", ").to_string());
+            if let Some(description) = &info.description {
+                lines.push(String::new());
+                lines.push(description.clone());
+            }
+        }
+        None => lines.push(String::from("Not in the database")),
+    }
+    if let Some(blurb) = &rom.blurb {
+        lines.push(String::new());
+        lines.push(blurb.clone());
+    }
+    lines
+        .into_iter()
+        .flat_map(|line| wrap(&line, COLUMNS - LIST_WIDTH - 1))
+        .collect()
+}
+
+/// Break `text` into lines of at most `width` characters, between words
+/// where possible.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        if !line.is_empty() && line.chars().count() + 1 + word.len() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        while word.len() > width {
+            lines.push(word.drain(..width).collect());
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.extend(word);
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
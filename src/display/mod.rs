@@ -1 +1,10 @@
+pub mod effects;
+pub mod menu;
+pub mod overlay;
+pub mod palette;
+pub mod persistence;
+pub mod scaling;
+pub mod screenshot;
+pub mod shader;
+pub mod terminal;
 pub mod window;
@@ -0,0 +1,304 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::constants::{WINDOW_HEIGHT, WINDOW_WIDTH};
+
+/// Overlay pixels per CHIP-8 pixel, so text can be much smaller than the game.
+pub const OVERLAY_SCALE: u32 = 4;
+pub const OVERLAY_WIDTH: u32 = WINDOW_WIDTH * OVERLAY_SCALE;
+pub const OVERLAY_HEIGHT: u32 = WINDOW_HEIGHT * OVERLAY_SCALE;
+
+/// How long a message stays up.
+pub const MESSAGE_DURATION: Duration = Duration::from_secs(2);
+
+/// The most messages shown at once. Older ones go first.
+const MAX_MESSAGES: usize = 3;
+
+const GLYPH_HEIGHT: u32 = 5;
+/// A glyph and the gap after it.
+const CELL_WIDTH: u32 = 4;
+/// A line of text on its dark box.
+const LINE_HEIGHT: u32 = GLYPH_HEIGHT + 2;
+
+const TEXT_COLOR: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+const BOX_COLOR: [u8; 4] = [0, 0, 0, 0xb0];
+
+/// A 3x5 font from ' ' to '_' in ASCII order, a row of three bits per byte
+/// with the left pixel highest. It has nothing to do with the CHIP-8 font,
+/// which only has hex digits.
+#[rustfmt::skip]
+const FONT: [[u8; 5]; 64] = [
+    [0b000, 0b000, 0b000, 0b000, 0b000], // ' '
+    [0b010, 0b010, 0b010, 0b000, 0b010], // !
+    [0b101, 0b101, 0b000, 0b000, 0b000], // "
+    [0b101, 0b111, 0b101, 0b111, 0b101], // #
+    [0b011, 0b110, 0b010, 0b011, 0b110], // $
+    [0b101, 0b001, 0b010, 0b100, 0b101], // %
+    [0b010, 0b101, 0b010, 0b101, 0b011], // &
+    [0b010, 0b010, 0b000, 0b000, 0b000], // '
+    [0b001, 0b010, 0b010, 0b010, 0b001], // (
+    [0b100, 0b010, 0b010, 0b010, 0b100], // )
+    [0b000, 0b101, 0b010, 0b101, 0b000], // *
+    [0b000, 0b010, 0b111, 0b010, 0b000], // +
+    [0b000, 0b000, 0b000, 0b010, 0b100], // ,
+    [0b000, 0b000, 0b111, 0b000, 0b000], // -
+    [0b000, 0b000, 0b000, 0b000, 0b010], // .
+    [0b001, 0b001, 0b010, 0b100, 0b100], // /
+    [0b111, 0b101, 0b101, 0b101, 0b111], // 0
+    [0b010, 0b110, 0b010, 0b010, 0b111], // 1
+    [0b111, 0b001, 0b111, 0b100, 0b111], // 2
+    [0b111, 0b001, 0b111, 0b001, 0b111], // 3
+    [0b101, 0b101, 0b111, 0b001, 0b001], // 4
+    [0b111, 0b100, 0b111, 0b001, 0b111], // 5
+    [0b111, 0b100, 0b111, 0b101, 0b111], // 6
+    [0b111, 0b001, 0b010, 0b010, 0b010], // 7
+    [0b111, 0b101, 0b111, 0b101, 0b111], // 8
+    [0b111, 0b101, 0b111, 0b001, 0b111], // 9
+    [0b000, 0b010, 0b000, 0b010, 0b000], // :
+    [0b000, 0b010, 0b000, 0b010, 0b100], // ;
+    [0b001, 0b010, 0b100, 0b010, 0b001], // <
+    [0b000, 0b111, 0b000, 0b111, 0b000], // =
+    [0b100, 0b010, 0b001, 0b010, 0b100], // >
+    [0b111, 0b001, 0b010, 0b000, 0b010], // ?
+    [0b010, 0b101, 0b111, 0b100, 0b011], // @
+    [0b010, 0b101, 0b111, 0b101, 0b101], // A
+    [0b110, 0b101, 0b110, 0b101, 0b110], // B
+    [0b011, 0b100, 0b100, 0b100, 0b011], // C
+    [0b110, 0b101, 0b101, 0b101, 0b110], // D
+    [0b111, 0b100, 0b110, 0b100, 0b111], // E
+    [0b111, 0b100, 0b110, 0b100, 0b100], // F
+    [0b011, 0b100, 0b101, 0b101, 0b011], // G
+    [0b101, 0b101, 0b111, 0b101, 0b101], // H
+    [0b111, 0b010, 0b010, 0b010, 0b111], // I
+    [0b001, 0b001, 0b001, 0b101, 0b010], // J
+    [0b101, 0b101, 0b110, 0b101, 0b101], // K
+    [0b100, 0b100, 0b100, 0b100, 0b111], // L
+    [0b101, 0b111, 0b101, 0b101, 0b101], // M
+    [0b110, 0b101, 0b101, 0b101, 0b101], // N
+    [0b010, 0b101, 0b101, 0b101, 0b010], // O
+    [0b110, 0b101, 0b110, 0b100, 0b100], // P
+    [0b010, 0b101, 0b101, 0b110, 0b011], // Q
+    [0b110, 0b101, 0b110, 0b101, 0b101], // R
+    [0b011, 0b100, 0b010, 0b001, 0b110], // S
+    [0b111, 0b010, 0b010, 0b010, 0b010], // T
+    [0b101, 0b101, 0b101, 0b101, 0b111], // U
+    [0b101, 0b101, 0b101, 0b101, 0b010], // V
+    [0b101, 0b101, 0b101, 0b111, 0b101], // W
+    [0b101, 0b101, 0b010, 0b101, 0b101], // X
+    [0b101, 0b101, 0b010, 0b010, 0b010], // Y
+    [0b111, 0b001, 0b010, 0b100, 0b111], // Z
+    [0b110, 0b100, 0b100, 0b100, 0b110], // [
+    [0b100, 0b100, 0b010, 0b001, 0b001], // \
+    [0b011, 0b001, 0b001, 0b001, 0b011], // ]
+    [0b010, 0b101, 0b000, 0b000, 0b000], // ^
+    [0b000, 0b000, 0b000, 0b000, 0b111], // _
+];
+
+/// The rows of `c`'s glyph. Lower case letters look like capitals, and
+/// characters the font doesn't have look like '?'.
+pub fn glyph(c: char) -> [u8; 5] {
+    let c = c.to_ascii_uppercase();
+    if (' '..='_').contains(&c) {
+        FONT[c as usize - ' ' as usize]
+    } else {
+        FONT['?' as usize - ' ' as usize]
+    }
+}
+
+/// The width of `text` drawn by `draw_text`, including its box.
+pub fn text_width(text: &str) -> u32 {
+    text.chars().count() as u32 * CELL_WIDTH + 1
+}
+
+/// Draw `text` into an RGBA `image` `width` pixels wide, on a dark box with
+/// its top left corner at (x, y). Anything past the edges is cut off.
+pub fn draw_text(image: &mut [u8], width: u32, x: u32, y: u32, text: &str) {
+    let height = image.len() as u32 / 4 / width;
+    let mut set = |x: u32, y: u32, color: [u8; 4]| {
+        if x < width && y < height {
+            let i = ((y * width + x) * 4) as usize;
+            image[i..i + 4].copy_from_slice(&color);
+        }
+    };
+    for dy in 0..LINE_HEIGHT {
+        for dx in 0..text_width(text) {
+            set(x + dx, y + dy, BOX_COLOR);
+        }
+    }
+    for (i, c) in text.chars().enumerate() {
+        let left = x + 1 + i as u32 * CELL_WIDTH;
+        for (dy, row) in glyph(c).iter().enumerate() {
+            for dx in 0..3 {
+                if row & (0b100 >> dx) != 0 {
+                    set(left + dx, y + 1 + dy as u32, TEXT_COLOR);
+                }
+            }
+        }
+    }
+}
+
+/// Write a rate with at most four characters, like "700", "12K" or "1.5M".
+pub fn format_rate(rate: f64) -> String {
+    if rate >= 1e6 {
+        format!("{:.1}M", rate / 1e6)
+    } else if rate >= 1e4 {
+        format!("{:.0}K", rate / 1e3)
+    } else if rate >= 1e3 {
+        format!("{:.1}K", rate / 1e3)
+    } else {
+        format!("{:.0}", rate)
+    }
+}
+
+/// Text drawn over the game: frame and instruction rates with the ROM's name,
+/// whether it's paused, and messages that go away after `MESSAGE_DURATION`.
+///
+/// The overlay is an RGBA image of `OVERLAY_WIDTH` by `OVERLAY_HEIGHT`,
+/// transparent where there's no text, stretched over the picture.
+pub struct Overlay {
+    /// Show the rates and the ROM's name.
+    pub show_stats: bool,
+    pub rom_name: String,
+    /// The lines of the open menu, drawn in place of the stats.
+    pub menu: Vec<String>,
+    paused: bool,
+    messages: VecDeque<(String, Instant)>,
+    /// Frames and the instruction count since `since`, which starts when
+    /// the first frame is counted.
+    frames: u32,
+    instructions: Option<u64>,
+    since: Instant,
+    fps: f64,
+    ips: f64,
+    /// What's in `image`, as (x, y, text).
+    lines: Vec<(u32, u32, String)>,
+    image: Vec<u8>,
+}
+
+impl Overlay {
+    pub fn new(rom_name: &str, now: Instant) -> Overlay {
+        Overlay {
+            show_stats: false,
+            rom_name: rom_name.to_string(),
+            menu: Vec::new(),
+            paused: false,
+            messages: VecDeque::new(),
+            frames: 0,
+            instructions: None,
+            since: now,
+            fps: 0.0,
+            ips: 0.0,
+            lines: Vec::new(),
+            image: vec![0; (OVERLAY_WIDTH * OVERLAY_HEIGHT * 4) as usize],
+        }
+    }
+
+    /// Show `text` until `MESSAGE_DURATION` after `now`.
+    pub fn message(&mut self, text: impl Into<String>, now: Instant) {
+        self.messages.push_back((text.into(), now));
+        if self.messages.len() > MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Show or hide the pause sign. Rates start being measured again, so
+    /// time spent paused doesn't count against them.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.instructions = None;
+    }
+
+    /// Count `frames` that ended with the interpreter having run
+    /// `instructions` in total. The rates are worked out once a second.
+    pub fn count_frames(&mut self, frames: u32, instructions: u64, now: Instant) {
+        let start = match self.instructions {
+            Some(start) => start,
+            None => {
+                self.instructions = Some(instructions);
+                self.since = now;
+                self.frames = 0;
+                return;
+            }
+        };
+        self.frames += frames;
+        let elapsed = now.duration_since(self.since).as_secs_f64();
+        if elapsed >= 1.0 {
+            self.fps = self.frames as f64 / elapsed;
+            self.ips = instructions.saturating_sub(start) as f64 / elapsed;
+            self.instructions = Some(instructions);
+            self.since = now;
+            self.frames = 0;
+        }
+    }
+
+    /// Frames per second over the last second.
+    pub fn fps(&self) -> f64 {
+        self.fps
+    }
+
+    /// Instructions per second over the last second.
+    pub fn ips(&self) -> f64 {
+        self.ips
+    }
+
+    /// Drop old messages and redraw the image if the text changed, returning
+    /// whether it did.
+    pub fn update(&mut self, now: Instant) -> bool {
+        while let Some((_, shown)) = self.messages.front() {
+            if now.duration_since(*shown) < MESSAGE_DURATION {
+                break;
+            }
+            self.messages.pop_front();
+        }
+        let lines = self.layout();
+        if lines == self.lines {
+            return false;
+        }
+        self.image.iter_mut().for_each(|byte| *byte = 0);
+        for (x, y, text) in lines.iter() {
+            draw_text(&mut self.image, OVERLAY_WIDTH, *x, *y, text);
+        }
+        self.lines = lines;
+        true
+    }
+
+    /// The text on the overlay as of the last `update`, top to bottom.
+    pub fn text(&self) -> Vec<&str> {
+        let mut lines: Vec<_> = self.lines.iter().collect();
+        lines.sort_by_key(|(x, y, _)| (*y, *x));
+        lines.iter().map(|(_, _, text)| text.as_str()).collect()
+    }
+
+    /// The RGBA image as of the last `update`.
+    pub fn image(&self) -> &[u8] {
+        &self.image
+    }
+
+    /// Stats in the top left, the pause sign in the top right, or the menu
+    /// across the top, and messages in the bottom left, newest at the bottom.
+    fn layout(&self) -> Vec<(u32, u32, String)> {
+        let mut lines = Vec::new();
+        for (i, text) in self.menu.iter().enumerate() {
+            let x = OVERLAY_WIDTH.saturating_sub(text_width(text)) / 2;
+            lines.push((x, i as u32 * LINE_HEIGHT, text.clone()));
+        }
+        if self.show_stats && self.menu.is_empty() {
+            lines.push((0, 0, self.rom_name.clone()));
+            let rates = format!("{:.0} FPS {} IPS", self.fps, format_rate(self.ips));
+            lines.push((0, LINE_HEIGHT, rates));
+        }
+        if self.paused && self.menu.is_empty() {
+            let text = "PAUSED";
+            lines.push((OVERLAY_WIDTH - text_width(text), 0, text.to_string()));
+        }
+        let bottom = OVERLAY_HEIGHT - LINE_HEIGHT * self.messages.len() as u32;
+        for (i, (text, _)) in self.messages.iter().enumerate() {
+            lines.push((0, bottom + i as u32 * LINE_HEIGHT, text.clone()));
+        }
+        lines
+    }
+}
@@ -0,0 +1,134 @@
+/// Names of the built-in themes.
+pub const THEMES: [&str; 5] = ["classic", "green", "amber", "lcd", "octo"];
+
+/// Colours for display pixels, indexed by which planes are lit: bit 0 for the
+/// first plane, bit 1 for XO-CHIP's second, and so on. So the first colour is
+/// the background, the second the foreground, and with two planes the third
+/// and fourth are the second plane alone and both together. Octo's four
+/// planes use all 16.
+///
+/// Palettes with fewer colours than there are planes draw the missing ones in
+/// the last colour.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Palette {
+    colors: Vec<[u8; 3]>,
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette::classic()
+    }
+}
+
+impl Palette {
+    /// The most colours a palette can have.
+    pub const MAX_COLORS: usize = 16;
+
+    /// A palette of 2 to 16 colours, background first.
+    pub fn new(colors: Vec<[u8; 3]>) -> Option<Palette> {
+        if (2..=Palette::MAX_COLORS).contains(&colors.len()) {
+            Some(Palette { colors })
+        } else {
+            None
+        }
+    }
+
+    fn from_hex(colors: [u32; 4]) -> Palette {
+        Palette {
+            colors: colors
+                .iter()
+                .map(|rgb| [(rgb >> 16) as u8, (rgb >> 8) as u8, *rgb as u8])
+                .collect(),
+        }
+    }
+
+    /// White on dark grey.
+    pub fn classic() -> Palette {
+        Palette::from_hex([0x151515, 0xffffff, 0x808080, 0xc0c0c0])
+    }
+
+    /// A green phosphor monitor.
+    pub fn green() -> Palette {
+        Palette::from_hex([0x0a1a0a, 0x33ff66, 0x1a9933, 0x99ffaa])
+    }
+
+    /// An amber phosphor monitor.
+    pub fn amber() -> Palette {
+        Palette::from_hex([0x1a1000, 0xffb000, 0x996600, 0xffd880])
+    }
+
+    /// Dark pixels on a green handheld LCD.
+    pub fn lcd() -> Palette {
+        Palette::from_hex([0x9bbc0f, 0x0f380f, 0x8bac0f, 0x306230])
+    }
+
+    /// Octo's default colours.
+    pub fn octo() -> Palette {
+        Palette::from_hex([0x996600, 0xffcc00, 0xff6600, 0x662200])
+    }
+
+    /// A built-in theme by name.
+    pub fn theme(name: &str) -> Option<Palette> {
+        match name.to_ascii_lowercase().as_str() {
+            "classic" => Some(Palette::classic()),
+            "green" => Some(Palette::green()),
+            "amber" => Some(Palette::amber()),
+            "lcd" => Some(Palette::lcd()),
+            "octo" => Some(Palette::octo()),
+            _ => None,
+        }
+    }
+
+    /// A theme name, or 2 to 16 comma separated `#rrggbb` colours.
+    pub fn parse(spec: &str) -> Result<Palette, String> {
+        if let Some(palette) = Palette::theme(spec) {
+            return Ok(palette);
+        }
+        if !spec.starts_with('#') {
+            return Err(format!(
+                "unknown theme {:?}, expected one of {} or #rrggbb colours",
+                spec,
+                THEMES.join(", ")
+            ));
+        }
+        let colors = spec
+            .split(',')
+            .map(|color| {
+                parse_color(color.trim()).ok_or_else(|| format!("invalid colour {:?}", color))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Palette::new(colors).ok_or_else(|| {
+            format!(
+                "palettes need 2 to {} colours, not {}",
+                Palette::MAX_COLORS,
+                spec.split(',').count()
+            )
+        })
+    }
+
+    pub fn colors(&self) -> &[[u8; 3]] {
+        &self.colors
+    }
+
+    pub fn background(&self) -> [u8; 3] {
+        self.colors[0]
+    }
+
+    /// The colour for a pixel with the planes in `planes` lit.
+    pub fn color(&self, planes: usize) -> [u8; 3] {
+        *self
+            .colors
+            .get(planes)
+            .unwrap_or_else(|| self.colors.last().unwrap())
+    }
+}
+
+/// Parse a `#rrggbb` colour.
+pub fn parse_color(color: &str) -> Option<[u8; 3]> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    Some([(value >> 16) as u8, (value >> 8) as u8, value as u8])
+}
@@ -0,0 +1,210 @@
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::display::palette::Palette;
+
+/// Names of the filters, in the order `Filter::next` goes through them.
+pub const FILTERS: [&str; 4] = ["off", "blend", "decay", "stable"];
+
+/// How much brightness a pixel keeps each frame after going out with `Filter::Decay`.
+pub const DEFAULT_DECAY: f32 = 0.6;
+
+/// The most frames `Filter::Stable` holds on to pixels being erased.
+const HOLD_FRAMES: u32 = 3;
+
+/// Below this `Filter::Decay` turns pixels off, since it can't be seen and
+/// would keep the picture changing for seconds.
+const FADED_OUT: f32 = 1.0 / 256.0;
+
+const PIXELS: usize = (WINDOW_WIDTH * WINDOW_HEIGHT) as usize;
+
+/// Ways to hide the flicker of sprites being erased and redrawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Filter {
+    /// Draw every frame as it is.
+    #[default]
+    Off,
+    /// Average each frame with the one before, so a sprite that is only on
+    /// every other frame shows at half brightness.
+    Blend,
+    /// Light pixels fully and let them fade out over a few frames, like the
+    /// phosphor of a CRT.
+    Decay,
+    /// Keep showing the last frame while pixels are only being erased, up to
+    /// a few frames, so sprites moved by erasing and redrawing don't blink.
+    Stable,
+}
+
+impl Filter {
+    pub fn from_name(name: &str) -> Option<Filter> {
+        match name.to_ascii_lowercase().as_str() {
+            "off" => Some(Filter::Off),
+            "blend" => Some(Filter::Blend),
+            "decay" => Some(Filter::Decay),
+            "stable" => Some(Filter::Stable),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        FILTERS[self as usize]
+    }
+
+    /// The filter after this one, wrapping around, for a hotkey to step through.
+    pub fn next(self) -> Filter {
+        Filter::from_name(FILTERS[(self as usize + 1) % FILTERS.len()]).unwrap()
+    }
+}
+
+/// What has been on the display recently, to draw it through a `Filter`.
+///
+/// Call `update` once for every frame the machine runs and `draw` the rows
+/// it says changed. `update` can be skipped while the display hasn't changed
+/// and `is_settled`.
+pub struct Persistence {
+    filter: Filter,
+    /// Brightness kept per frame with `Filter::Decay`, from 0 to 1.
+    pub decay: f32,
+    /// How lit each pixel of each plane looks, from 0 to 1.
+    intensity: [Vec<f32>; 2],
+    previous: [[u64; 32]; 2],
+    shown: [[u64; 32]; 2],
+    held: u32,
+    settled: bool,
+}
+
+impl Default for Persistence {
+    fn default() -> Persistence {
+        Persistence::new(Filter::default())
+    }
+}
+
+impl Persistence {
+    pub fn new(filter: Filter) -> Persistence {
+        Persistence {
+            filter,
+            decay: DEFAULT_DECAY,
+            intensity: [vec![0.0; PIXELS], vec![0.0; PIXELS]],
+            previous: [[0; 32]; 2],
+            shown: [[0; 32]; 2],
+            held: 0,
+            settled: false,
+        }
+    }
+
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+        self.settled = false;
+    }
+
+    /// Whether another `update` with the same planes would change nothing,
+    /// so nothing is still fading or being held.
+    pub fn is_settled(&self) -> bool {
+        self.settled
+    }
+
+    /// Take in the planes at the end of a frame. Returns the rows whose
+    /// picture changed, bit y for row y.
+    pub fn update(&mut self, planes: [&[u64; 32]; 2]) -> u32 {
+        let current = [*planes[0], *planes[1]];
+        let state = (self.previous, self.shown, self.held);
+        let old_intensity = self.intensity.clone();
+        match self.filter {
+            Filter::Off => self.set(|plane, x, y, _| lit(&current[plane], x, y)),
+            Filter::Blend => {
+                let previous = self.previous;
+                self.set(|plane, x, y, _| {
+                    (lit(&current[plane], x, y) + lit(&previous[plane], x, y)) / 2.0
+                });
+            }
+            Filter::Decay => {
+                let decay = self.decay;
+                self.set(|plane, x, y, old| {
+                    let faded = old * decay;
+                    let faded = if faded < FADED_OUT { 0.0 } else { faded };
+                    lit(&current[plane], x, y).max(faded)
+                });
+            }
+            Filter::Stable => {
+                let erasing = current != self.shown
+                    && current
+                        .iter()
+                        .flatten()
+                        .zip(self.shown.iter().flatten())
+                        .all(|(now, shown)| now & !shown == 0);
+                if erasing && self.held < HOLD_FRAMES {
+                    self.held += 1;
+                } else {
+                    self.held = 0;
+                    self.shown = current;
+                }
+                let shown = self.shown;
+                self.set(|plane, x, y, _| lit(&shown[plane], x, y));
+            }
+        }
+        self.previous = current;
+        let mut changed = 0;
+        for (new, old) in self.intensity.iter().zip(old_intensity.iter()) {
+            let rows = new.chunks_exact(64).zip(old.chunks_exact(64));
+            for (y, (new, old)) in rows.enumerate() {
+                if new != old {
+                    changed |= 1 << y;
+                }
+            }
+        }
+        self.settled = changed == 0 && state == (self.previous, self.shown, self.held);
+        changed
+    }
+
+    fn set(&mut self, intensity: impl Fn(usize, usize, usize, f32) -> f32) {
+        for (plane, intensities) in self.intensity.iter_mut().enumerate() {
+            for (i, value) in intensities.iter_mut().enumerate() {
+                *value = intensity(plane, i % 64, i / 64, *value);
+            }
+        }
+    }
+
+    /// How lit a pixel of a plane looks, from 0 to 1.
+    pub fn intensity(&self, plane: usize, x: usize, y: usize) -> f32 {
+        self.intensity[plane][y * 64 + x]
+    }
+
+    /// Render `rows`, bit y for row y, into an RGBA frame, mixing `palette`'s
+    /// colours by how lit each plane looks. Other rows are left as they were;
+    /// `ALL_ROWS` draws everything.
+    pub fn draw(&self, frame: &mut [u8], palette: &Palette, rows: u32) {
+        for (i, pixel) in frame.chunks_exact_mut(4).enumerate() {
+            if rows & (1 << (i / 64)) == 0 {
+                continue;
+            }
+            let (first, second) = (self.intensity[0][i], self.intensity[1][i]);
+            let weights = [
+                (1.0 - first) * (1.0 - second),
+                first * (1.0 - second),
+                (1.0 - first) * second,
+                first * second,
+            ];
+            let mut rgb = [0.0; 3];
+            for (planes, weight) in weights.iter().enumerate() {
+                if *weight > 0.0 {
+                    for (channel, value) in rgb.iter_mut().zip(palette.color(planes).iter()) {
+                        *channel += weight * *value as f32;
+                    }
+                }
+            }
+            for (channel, value) in pixel.iter_mut().zip(rgb.iter()) {
+                *channel = value.round() as u8;
+            }
+            pixel[3] = 0xff;
+        }
+    }
+}
+
+fn lit(plane: &[u64; 32], x: usize, y: usize) -> f32 {
+    (plane[y] >> x & 1) as f32
+}
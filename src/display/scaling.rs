@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+/// How the picture is scaled to fit the window.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scaling {
+    /// The largest whole number of window pixels per pixel that fits, so
+    /// every pixel is the same size.
+    #[default]
+    Integer,
+    /// As large as fits, even if pixels end up slightly different sizes.
+    Fractional,
+}
+
+/// Where the picture goes in the window, in physical pixels. The rest is
+/// left black.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Scaling {
+    /// Centre a picture of `picture` size in a window of `window` size,
+    /// keeping its aspect ratio. Pictures never shrink below one window
+    /// pixel per pixel.
+    pub fn viewport(self, picture: (u32, u32), window: (u32, u32)) -> Viewport {
+        let (picture_width, picture_height) = (picture.0 as f32, picture.1 as f32);
+        let (window_width, window_height) = (window.0 as f32, window.1 as f32);
+        let fit = (window_width / picture_width).min(window_height / picture_height);
+        let scale = match self {
+            Scaling::Integer => fit.floor(),
+            Scaling::Fractional => fit,
+        }
+        .max(1.0);
+        let (width, height) = (picture_width * scale, picture_height * scale);
+        Viewport {
+            x: ((window_width - width) / 2.0).max(0.0).floor(),
+            y: ((window_height - height) / 2.0).max(0.0).floor(),
+            width,
+            height,
+        }
+    }
+}
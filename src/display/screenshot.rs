@@ -0,0 +1,157 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::constants::{WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::display::palette::Palette;
+
+/// Image formats screenshots can be saved in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    /// Plain (P1) portable bitmap, one character per pixel, so it diffs nicely.
+    Pbm,
+}
+
+impl ImageFormat {
+    /// Guess the format from a file extension.
+    pub fn from_path(path: &Path) -> Option<ImageFormat> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => Some(ImageFormat::Png),
+            "pbm" => Some(ImageFormat::Pbm),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Pbm => "pbm",
+        }
+    }
+
+    /// A `screenshot-<milliseconds since epoch>` file name in the working directory.
+    pub fn timestamped_path(self) -> PathBuf {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_millis());
+        PathBuf::from(format!("screenshot-{}.{}", millis, self.extension()))
+    }
+}
+
+/// Settings for capturing the display as an image.
+#[derive(Clone, Debug)]
+pub struct Screenshot {
+    /// Size of each CHIP-8 pixel in image pixels.
+    pub scale: u32,
+    /// Colours for unlit and lit pixels.
+    pub palette: Palette,
+}
+
+impl Default for Screenshot {
+    fn default() -> Screenshot {
+        Screenshot {
+            scale: 1,
+            palette: Palette::default(),
+        }
+    }
+}
+
+impl Screenshot {
+    pub fn width(&self) -> u32 {
+        WINDOW_WIDTH * self.scale
+    }
+
+    pub fn height(&self) -> u32 {
+        WINDOW_HEIGHT * self.scale
+    }
+
+    /// Which planes image pixel (x, y) is lit on, bit 0 for the first.
+    fn planes_lit(&self, planes: [&[u64; 32]; 2], x: u32, y: u32) -> usize {
+        planes_lit(planes, x / self.scale, y / self.scale)
+    }
+
+    /// Render both display planes as RGBA bytes, row by row, in the
+    /// palette's colours the way `Interpreter::draw` does.
+    pub fn to_rgba(&self, planes: [&[u64; 32]; 2]) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity((self.width() * self.height() * 4) as usize);
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let color = self.palette.color(self.planes_lit(planes, x, y));
+                rgba.extend_from_slice(&color);
+                rgba.push(0xff);
+            }
+        }
+        rgba
+    }
+
+    /// Save both display planes in the format given by the file extension.
+    pub fn save(&self, planes: [&[u64; 32]; 2], path: &Path) -> io::Result<()> {
+        let format = ImageFormat::from_path(path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "screenshots must be .png or .pbm",
+            )
+        })?;
+        let mut file = BufWriter::new(File::create(path)?);
+        match format {
+            ImageFormat::Png => self.write_png(planes, &mut file),
+            ImageFormat::Pbm => self.write_pbm(planes, &mut file),
+        }?;
+        file.flush()
+    }
+
+    pub fn write_png<W: Write>(&self, planes: [&[u64; 32]; 2], writer: W) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(writer, self.width(), self.height());
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&self.to_rgba(planes)))
+            .map_err(io::Error::other)
+    }
+
+    /// PBM is black and white only, so pixels lit on either plane are black
+    /// whatever the colours.
+    pub fn write_pbm<W: Write>(&self, planes: [&[u64; 32]; 2], mut writer: W) -> io::Result<()> {
+        writeln!(writer, "P1\n{} {}", self.width(), self.height())?;
+        for y in 0..self.height() {
+            let row: String = (0..self.width())
+                .map(|x| {
+                    if self.planes_lit(planes, x, y) != 0 {
+                        '1'
+                    } else {
+                        '0'
+                    }
+                })
+                .collect();
+            writeln!(writer, "{}", row)?;
+        }
+        Ok(())
+    }
+}
+
+/// Characters `to_ascii` uses for pixels lit on no plane, the first, the
+/// second and both.
+const ASCII_PIXELS: [char; 4] = ['.', '#', 'o', '@'];
+
+/// Render both display planes as text, one line per row, with `#` for pixels
+/// lit on the first plane, `o` on the second and `@` on both.
+pub fn to_ascii(planes: [&[u64; 32]; 2]) -> String {
+    let mut text = String::with_capacity(((WINDOW_WIDTH + 1) * WINDOW_HEIGHT) as usize);
+    for y in 0..WINDOW_HEIGHT {
+        for x in 0..WINDOW_WIDTH {
+            text.push(ASCII_PIXELS[planes_lit(planes, x, y)]);
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Which planes display pixel (x, y) is lit on, bit 0 for the first.
+fn planes_lit(planes: [&[u64; 32]; 2], x: u32, y: u32) -> usize {
+    let lit = |plane: &[u64; 32]| (plane[y as usize] >> x & 1) as usize;
+    lit(planes[0]) | lit(planes[1]) << 1
+}
@@ -0,0 +1,266 @@
+use pixels::wgpu::{self, util::DeviceExt};
+use pixels::{Pixels, PixelsContext};
+
+use crate::constants::{WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::display::effects::Effects;
+use crate::display::overlay::{OVERLAY_HEIGHT, OVERLAY_WIDTH};
+use crate::display::scaling::{Scaling, Viewport};
+
+/// The effects shader, in the WGSL wgpu 0.7 understands.
+pub const CRT_SHADER: &str = include_str!("crt.wgsl");
+
+/// The picture is scaled by display pixels, whatever size the texture is
+/// drawn at on the CPU.
+const DISPLAY_SIZE: (u32, u32) = (WINDOW_WIDTH, WINDOW_HEIGHT);
+
+/// Draws the pixels texture to the window through `CRT_SHADER`, in place of
+/// pixels' own scaling renderer, which only scales by whole numbers. With no
+/// effects it just scales. An `Overlay` image is drawn on top.
+pub struct CrtRenderer {
+    uniform_buffer: wgpu::Buffer,
+    overlay_texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    render_pipeline: wgpu::RenderPipeline,
+    texture_size: (u32, u32),
+    pub scaling: Scaling,
+    viewport: Viewport,
+}
+
+impl CrtRenderer {
+    /// `target_format` must match the surface, which is pixels' default
+    /// `Bgra8UnormSrgb` unless the builder changed it.
+    pub fn new(
+        pixels: &Pixels,
+        target_format: wgpu::TextureFormat,
+        effects: Effects,
+        scaling: Scaling,
+        surface_size: (u32, u32),
+    ) -> CrtRenderer {
+        let context = pixels.context();
+        let device = &context.device;
+        let module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("crt_shader"),
+            source: wgpu::ShaderSource::Wgsl(CRT_SHADER.into()),
+            flags: wgpu::ShaderFlags::VALIDATION,
+        });
+        let texture_view = context
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("crt_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: 1.0,
+            compare: None,
+            anisotropy_clamp: None,
+            border_color: None,
+        });
+        let texture_size = (context.texture_extent.width, context.texture_extent.height);
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("crt_uniform_buffer"),
+            contents: &uniform_bytes(effects, texture_size),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+        let overlay_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("crt_overlay_texture"),
+            size: OVERLAY_EXTENT,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+        let overlay_view = overlay_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("crt_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler {
+                        filtering: true,
+                        comparison: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("crt_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer {
+                        buffer: &uniform_buffer,
+                        offset: 0,
+                        size: None,
+                    },
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&overlay_view),
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("crt_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("crt_pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: "fs_main",
+                targets: &[wgpu::ColorTargetState {
+                    format: target_format,
+                    color_blend: wgpu::BlendState::REPLACE,
+                    alpha_blend: wgpu::BlendState::REPLACE,
+                    write_mask: wgpu::ColorWrite::ALL,
+                }],
+            }),
+        });
+
+        let crt = CrtRenderer {
+            uniform_buffer,
+            overlay_texture,
+            bind_group,
+            render_pipeline,
+            texture_size,
+            scaling,
+            viewport: scaling.viewport(DISPLAY_SIZE, surface_size),
+        };
+        crt.set_overlay(context, &[0; (OVERLAY_WIDTH * OVERLAY_HEIGHT * 4) as usize]);
+        crt
+    }
+
+    /// Change the effects drawn from the next frame on.
+    pub fn set_effects(&self, context: &PixelsContext, effects: Effects) {
+        context.queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            &uniform_bytes(effects, self.texture_size),
+        );
+    }
+
+    /// Replace the overlay with an RGBA image of `OVERLAY_WIDTH` by
+    /// `OVERLAY_HEIGHT`.
+    pub fn set_overlay(&self, context: &PixelsContext, image: &[u8]) {
+        context.queue.write_texture(
+            wgpu::TextureCopyView {
+                texture: &self.overlay_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            image,
+            wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row: OVERLAY_WIDTH * 4,
+                rows_per_image: OVERLAY_HEIGHT,
+            },
+            OVERLAY_EXTENT,
+        );
+    }
+
+    /// Fit the picture to a surface of a new size, in physical pixels.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.viewport = self.scaling.viewport(DISPLAY_SIZE, (width, height));
+    }
+
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, render_target: &wgpu::TextureView) {
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("crt_render_pass"),
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: render_target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+        let Viewport {
+            x,
+            y,
+            width,
+            height,
+        } = self.viewport;
+        rpass.set_viewport(x, y, width, height, 0.0, 1.0);
+        rpass.set_pipeline(&self.render_pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+const OVERLAY_EXTENT: wgpu::Extent3d = wgpu::Extent3d {
+    width: OVERLAY_WIDTH,
+    height: OVERLAY_HEIGHT,
+    depth: 1,
+};
+
+/// The shader's `Params`: the effects, then the texture size padded to a vec4.
+fn uniform_bytes(effects: Effects, texture_size: (u32, u32)) -> Vec<u8> {
+    let size = [texture_size.0 as f32, texture_size.1 as f32, 0.0, 0.0];
+    effects
+        .to_uniform()
+        .iter()
+        .chain(size.iter())
+        .flat_map(|value| value.to_ne_bytes())
+        .collect()
+}
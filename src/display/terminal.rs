@@ -0,0 +1,70 @@
+use crate::constants::{WINDOW_HEIGHT, WINDOW_WIDTH};
+
+/// How pixels are packed into terminal characters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextMode {
+    /// `▀`, `▄` and `█`, one column by two rows of pixels per character.
+    HalfBlocks,
+    /// Braille patterns, two columns by four rows of pixels per character.
+    Braille,
+}
+
+/// Render the display as lines of text for a terminal. Text has one colour,
+/// so a pixel lit on either plane is drawn.
+pub fn render(planes: [&[u64; 32]; 2], mode: TextMode) -> Vec<String> {
+    let mut video_output = *planes[0];
+    for (row, second) in video_output.iter_mut().zip(planes[1].iter()) {
+        *row |= second;
+    }
+    match mode {
+        TextMode::HalfBlocks => render_half_blocks(&video_output),
+        TextMode::Braille => render_braille(&video_output),
+    }
+}
+
+fn pixel(video_output: &[u64; 32], x: u32, y: u32) -> bool {
+    video_output[y as usize] & (1 << x) != 0
+}
+
+fn render_half_blocks(video_output: &[u64; 32]) -> Vec<String> {
+    (0..WINDOW_HEIGHT)
+        .step_by(2)
+        .map(|y| {
+            (0..WINDOW_WIDTH)
+                .map(
+                    |x| match (pixel(video_output, x, y), pixel(video_output, x, y + 1)) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    },
+                )
+                .collect()
+        })
+        .collect()
+}
+
+fn render_braille(video_output: &[u64; 32]) -> Vec<String> {
+    // Dot bits of a braille cell, indexed by [row][column]
+    const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+    (0..WINDOW_HEIGHT)
+        .step_by(4)
+        .map(|y| {
+            (0..WINDOW_WIDTH)
+                .step_by(2)
+                .map(|x| {
+                    let mut cell = 0x2800;
+                    for (row, dots) in DOTS.iter().enumerate() {
+                        for (column, dot) in dots.iter().enumerate() {
+                            if pixel(video_output, x + column as u32, y + row as u32) {
+                                cell |= dot;
+                            }
+                        }
+                    }
+                    std::char::from_u32(cell).unwrap()
+                })
+                .collect()
+        })
+        .collect()
+}
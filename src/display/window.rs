@@ -9,17 +9,14 @@ use crate::constants::{WINDOW_HEIGHT, WINDOW_WIDTH};
 ///
 /// # Returns
 ///
-/// Tuple of `(window, surface, width, height, hidpi_factor)`
+/// Tuple of `(window, width, height)`
 /// `width` and `height` are in `PhysicalSize` units.
-pub fn create_window(
-    title: &str,
-    event_loop: &EventLoop<()>,
-) -> (winit::window::Window, u32, u32, f64) {
+pub fn create_window(title: &str, event_loop: &EventLoop<()>) -> (winit::window::Window, u32, u32) {
     // Create a hidden window so we can estimate a good default window size
     let window = winit::window::WindowBuilder::new()
         .with_visible(false)
         .with_title(title)
-        .build(&event_loop)
+        .build(event_loop)
         .unwrap();
     let hidpi_factor = window.scale_factor();
 
@@ -55,6 +52,5 @@ pub fn create_window(
         window,
         size.width.round() as u32,
         size.height.round() as u32,
-        hidpi_factor,
     )
 }
@@ -0,0 +1,207 @@
+use std::fs::read;
+use std::io;
+
+use serde::Serialize;
+
+use crate::database::RomDatabase;
+use crate::interpreter::code_map::SelfModification;
+use crate::interpreter::fault::Fault;
+use crate::interpreter::interpreter::Interpreter;
+
+/// A key held down for a number of frames.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyPress {
+    pub frame: u32,
+    pub key: u8,
+    pub frames: u32,
+}
+
+/// Key presses to replay while running headless.
+#[derive(Clone, Debug, Default)]
+pub struct Script {
+    pub presses: Vec<KeyPress>,
+}
+
+impl Script {
+    /// Parse a script with one `FRAME KEY [FRAMES]` press per line.
+    ///
+    /// `KEY` is a hex keypad digit, `FRAMES` defaults to 1 and `#` starts a comment.
+    pub fn parse(text: &str) -> Result<Script, String> {
+        let mut script = Script::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let press = parse_press(&fields).map_err(|e| format!("line {}: {}", number + 1, e))?;
+            script.presses.push(press);
+        }
+        Ok(script)
+    }
+
+    /// Parse a single `FRAME:KEY[:FRAMES]` press, as given on the command line.
+    pub fn parse_press(text: &str) -> Result<KeyPress, String> {
+        parse_press(&text.split(':').collect::<Vec<_>>())
+    }
+
+    /// Set the keypad to the state the script wants at `frame`.
+    pub fn apply(&self, frame: u32, keypad: &mut [bool; 16]) {
+        *keypad = [false; 16];
+        for press in &self.presses {
+            if frame >= press.frame && frame - press.frame < press.frames {
+                keypad[press.key as usize] = true;
+            }
+        }
+    }
+}
+
+fn parse_press(fields: &[&str]) -> Result<KeyPress, String> {
+    if fields.len() < 2 || fields.len() > 3 {
+        return Err(String::from("expected FRAME KEY [FRAMES]"));
+    }
+    let frame = fields[0]
+        .parse()
+        .map_err(|_| format!("invalid frame {:?}", fields[0]))?;
+    let key = u8::from_str_radix(fields[1], 16)
+        .ok()
+        .filter(|key| *key < 16)
+        .ok_or_else(|| format!("invalid key {:?}", fields[1]))?;
+    let frames = match fields.get(2) {
+        Some(frames) => frames
+            .parse()
+            .map_err(|_| format!("invalid frame count {:?}", frames))?,
+        None => 1,
+    };
+    Ok(KeyPress { frame, key, frames })
+}
+
+/// Conditions that end a headless run early.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopCondition {
+    /// The program counter reaches an address.
+    ProgramCounter(u16),
+    /// The program jumps to itself, the usual way CHIP-8 programs halt.
+    Loop,
+    /// The interpreter faults.
+    Fault,
+}
+
+/// Why a headless run ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// All requested frames were run.
+    Frames,
+    ProgramCounter(u16),
+    Loop(u16),
+    Fault(Fault),
+}
+
+/// Runs an interpreter without a window.
+pub struct Runner {
+    pub interpreter: Interpreter,
+    pub script: Script,
+    pub stop_conditions: Vec<StopCondition>,
+    /// Number of frames run so far.
+    pub frame: u32,
+}
+
+impl Runner {
+    pub fn new(interpreter: Interpreter) -> Runner {
+        Runner {
+            interpreter,
+            script: Script::default(),
+            stop_conditions: Vec::new(),
+            frame: 0,
+        }
+    }
+
+    /// Load a ROM and configure it from the built-in database like the frontends do.
+    /// Fails if it can't be read or doesn't fit in memory.
+    pub fn from_rom(rom: &str) -> io::Result<Runner> {
+        let (interpreter, _) = RomDatabase::builtin()
+            .load(read(rom)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Runner::new(interpreter))
+    }
+
+    /// Run up to `frames` 60 Hz frames, stopping early if a stop condition is met.
+    pub fn run(&mut self, frames: u32) -> StopReason {
+        for _ in 0..frames {
+            self.script.apply(self.frame, &mut self.interpreter.keypad);
+            self.interpreter.start_frame();
+            loop {
+                let program_counter = self.interpreter.program_counter;
+                if !self.interpreter.update_within_frame() {
+                    break;
+                }
+                if let Some(reason) = self.check(program_counter) {
+                    return reason;
+                }
+            }
+            self.interpreter.vblank();
+            self.frame += 1;
+        }
+        StopReason::Frames
+    }
+
+    /// Check the stop conditions after running the instruction at `program_counter`.
+    fn check(&self, program_counter: u16) -> Option<StopReason> {
+        for condition in &self.stop_conditions {
+            match condition {
+                StopCondition::ProgramCounter(address) => {
+                    if self.interpreter.program_counter == *address {
+                        return Some(StopReason::ProgramCounter(*address));
+                    }
+                }
+                StopCondition::Loop => {
+                    let memory = &self.interpreter.memory;
+                    let address = program_counter as usize;
+                    let jump = address < memory.len() && memory.peek(address) & 0xf0 == 0x10;
+                    if jump && self.interpreter.program_counter == program_counter {
+                        return Some(StopReason::Loop(program_counter));
+                    }
+                }
+                StopCondition::Fault => {
+                    if let Some(fault) = self.interpreter.fault {
+                        return Some(StopReason::Fault(fault));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Snapshot of the CPU registers, for dumping as JSON.
+#[derive(Clone, Debug, Serialize)]
+pub struct MachineState {
+    pub program_counter: u16,
+    pub index: u16,
+    pub registers: [u8; 16],
+    pub stack: Vec<u16>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub waiting_for_key: bool,
+    pub fault: Option<String>,
+    /// The first address below 0x200 the program wrote to.
+    pub reserved_write: Option<usize>,
+    pub self_modifications: Vec<SelfModification>,
+}
+
+impl MachineState {
+    pub fn new(interpreter: &Interpreter) -> MachineState {
+        MachineState {
+            program_counter: interpreter.program_counter,
+            index: interpreter.index,
+            registers: interpreter.registers,
+            stack: interpreter.stack[..interpreter.stack_pointer].to_vec(),
+            delay_timer: interpreter.delay_timer,
+            sound_timer: interpreter.sound_timer,
+            waiting_for_key: interpreter.waiting_for_key(),
+            fault: interpreter.fault.map(|fault| fault.to_string()),
+            reserved_write: interpreter.memory.reserved_write(),
+            self_modifications: interpreter.code_map.modifications.clone(),
+        }
+    }
+}
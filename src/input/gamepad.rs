@@ -0,0 +1,421 @@
+//! Gamepads through the Linux joystick API (`/dev/input/js*`), so no extra
+//! libraries are needed there, and through gilrs everywhere else. Button
+//! numbers follow the xpad driver used by Xbox style controllers, and gilrs'
+//! events are translated to match.
+
+use std::collections::HashMap;
+
+use controllers::Controllers;
+
+/// How far a stick has to move to count as a direction.
+const AXIS_THRESHOLD: i16 = 16384;
+
+/// Controls on a gamepad, named like the chip-8-database's key actions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Button {
+    Up,
+    Down,
+    Left,
+    Right,
+    A,
+    B,
+    X,
+    Y,
+    Select,
+    Start,
+}
+
+impl Button {
+    pub const ALL: [Button; 10] = [
+        Button::Up,
+        Button::Down,
+        Button::Left,
+        Button::Right,
+        Button::A,
+        Button::B,
+        Button::X,
+        Button::Y,
+        Button::Select,
+        Button::Start,
+    ];
+
+    /// The database action name for this button, such as `up` for player 1 and
+    /// `player2Up` for player 2.
+    pub fn action(self, player: usize) -> String {
+        let name = match self {
+            Button::Up => "up",
+            Button::Down => "down",
+            Button::Left => "left",
+            Button::Right => "right",
+            Button::A => "a",
+            Button::B => "b",
+            Button::X => "x",
+            Button::Y => "y",
+            Button::Select => "select",
+            Button::Start => "start",
+        };
+        if player == 1 {
+            return String::from(name);
+        }
+        let mut capitalized = name.to_string();
+        capitalized[..1].make_ascii_uppercase();
+        format!("player{}{}", player, capitalized)
+    }
+}
+
+/// Which keypad key each button of one player's controller presses.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GamepadMap {
+    pub buttons: HashMap<Button, u8>,
+}
+
+impl GamepadMap {
+    /// The D-pad on 2, 8, 4 and 6 like a numpad, and A on 5, which suits many games.
+    pub fn standard() -> GamepadMap {
+        let buttons = [
+            (Button::Up, 0x2),
+            (Button::Down, 0x8),
+            (Button::Left, 0x4),
+            (Button::Right, 0x6),
+            (Button::A, 0x5),
+        ];
+        GamepadMap {
+            buttons: buttons.iter().copied().collect(),
+        }
+    }
+
+    /// The map for `player` given named actions from the database or key
+    /// config. A player with no actions of their own gets player 1's map, and
+    /// player 1 falls back to `standard`, so every controller does something.
+    pub fn from_actions(actions: &HashMap<String, u8>, player: usize) -> GamepadMap {
+        let buttons: HashMap<Button, u8> = Button::ALL
+            .iter()
+            .filter_map(|button| {
+                let key = actions.get(&button.action(player))?;
+                Some((*button, key & 0xf))
+            })
+            .collect();
+        if buttons.is_empty() {
+            return match player {
+                1 => GamepadMap::standard(),
+                _ => GamepadMap::from_actions(actions, 1),
+            };
+        }
+        GamepadMap { buttons }
+    }
+}
+
+/// One event read from a joystick device.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JoystickEvent {
+    Button { number: u8, pressed: bool },
+    Axis { number: u8, value: i16 },
+}
+
+impl JoystickEvent {
+    /// Decode a `struct js_event`: a timestamp, value, type and number.
+    pub fn parse(bytes: [u8; 8]) -> Option<JoystickEvent> {
+        let value = i16::from_ne_bytes([bytes[4], bytes[5]]);
+        let number = bytes[7];
+        // The initial state is sent with 0x80 set
+        match bytes[6] & !0x80 {
+            0x01 => Some(JoystickEvent::Button {
+                number,
+                pressed: value != 0,
+            }),
+            0x02 => Some(JoystickEvent::Axis { number, value }),
+            _ => None,
+        }
+    }
+}
+
+/// What is held on one controller.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GamepadState {
+    buttons: u32,
+    axes: [i16; 8],
+}
+
+impl GamepadState {
+    pub fn apply(&mut self, event: JoystickEvent) {
+        match event {
+            JoystickEvent::Button { number, pressed } if number < 32 => {
+                if pressed {
+                    self.buttons |= 1 << number;
+                } else {
+                    self.buttons &= !(1 << number);
+                }
+            }
+            JoystickEvent::Axis { number, value } if (number as usize) < self.axes.len() => {
+                self.axes[number as usize] = value;
+            }
+            _ => {}
+        }
+    }
+
+    /// Directions come from the left stick (axes 0 and 1) or the D-pad (6 and 7).
+    pub fn held(&self, button: Button) -> bool {
+        let axis = |numbers: [usize; 2], positive: bool| {
+            numbers.iter().any(|number| {
+                let value = self.axes[*number];
+                if positive {
+                    value >= AXIS_THRESHOLD
+                } else {
+                    value <= -AXIS_THRESHOLD
+                }
+            })
+        };
+        let button_held = |number: u32| self.buttons & (1 << number) != 0;
+        match button {
+            Button::Up => axis([1, 7], false),
+            Button::Down => axis([1, 7], true),
+            Button::Left => axis([0, 6], false),
+            Button::Right => axis([0, 6], true),
+            Button::A => button_held(0),
+            Button::B => button_held(1),
+            Button::X => button_held(2),
+            Button::Y => button_held(3),
+            Button::Select => button_held(6),
+            Button::Start => button_held(7),
+        }
+    }
+
+    /// Press the keypad keys `map` gives the held buttons, leaving others alone.
+    pub fn press(&self, map: &GamepadMap, keypad: &mut [bool; 16]) {
+        for (button, key) in &map.buttons {
+            if self.held(*button) {
+                keypad[*key as usize] = true;
+            }
+        }
+    }
+}
+
+/// Every connected controller, in the order they were found: the first is
+/// player 1.
+pub struct Gamepads {
+    controllers: Controllers,
+    /// Maps for players 1, 2, ...; players without one use the last.
+    pub maps: Vec<GamepadMap>,
+}
+
+impl Gamepads {
+    pub fn new(maps: Vec<GamepadMap>) -> Gamepads {
+        Gamepads {
+            controllers: Controllers::new(),
+            maps,
+        }
+    }
+
+    /// Maps for both players of a ROM from its named actions.
+    pub fn for_actions(actions: &HashMap<String, u8>) -> Gamepads {
+        Gamepads::new(
+            (1..=2)
+                .map(|player| GamepadMap::from_actions(actions, player))
+                .collect(),
+        )
+    }
+
+    pub fn connected(&self) -> usize {
+        self.controllers.states().count()
+    }
+
+    /// Read what changed on every controller, pick up new ones, and press the
+    /// keypad keys their held buttons map to.
+    pub fn update(&mut self, keypad: &mut [bool; 16]) {
+        self.controllers.update();
+        for (player, state) in self.controllers.states().enumerate() {
+            if let Some(map) = self.maps.get(player).or_else(|| self.maps.last()) {
+                state.press(map, keypad);
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod controllers {
+    use std::fs::{File, OpenOptions};
+    use std::io::{self, Read};
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, Instant};
+
+    use super::{GamepadState, JoystickEvent};
+
+    /// How often to look for newly plugged in controllers.
+    const SCAN_INTERVAL: Duration = Duration::from_secs(1);
+    /// The most controllers looked for.
+    const MAX_DEVICES: usize = 8;
+
+    /// Joystick devices, in device order.
+    pub struct Controllers {
+        devices: Vec<Device>,
+        last_scan: Option<Instant>,
+    }
+
+    struct Device {
+        path: PathBuf,
+        file: File,
+        state: GamepadState,
+    }
+
+    impl Controllers {
+        pub fn new() -> Controllers {
+            Controllers {
+                devices: Vec::new(),
+                last_scan: None,
+            }
+        }
+
+        pub fn states(&self) -> impl Iterator<Item = &GamepadState> {
+            self.devices.iter().map(|device| &device.state)
+        }
+
+        /// Read what changed on every controller and pick up new ones.
+        pub fn update(&mut self) {
+            if self
+                .last_scan
+                .is_none_or(|time| time.elapsed() >= SCAN_INTERVAL)
+            {
+                self.scan();
+                self.last_scan = Some(Instant::now());
+            }
+            self.devices
+                .retain_mut(|device| device.read_events().is_ok());
+        }
+
+        /// Open controllers that have appeared since the last scan.
+        fn scan(&mut self) {
+            for number in 0..MAX_DEVICES {
+                let path = PathBuf::from(format!("/dev/input/js{}", number));
+                if self.devices.iter().any(|device| device.path == path) {
+                    continue;
+                }
+                if let Ok(file) = open_nonblocking(&path) {
+                    self.devices.push(Device {
+                        path,
+                        file,
+                        state: GamepadState::default(),
+                    });
+                }
+            }
+            self.devices.sort_by(|a, b| a.path.cmp(&b.path));
+        }
+    }
+
+    impl Device {
+        /// Apply every waiting event, failing once the controller is unplugged.
+        fn read_events(&mut self) -> io::Result<()> {
+            let mut bytes = [0; 8];
+            loop {
+                match self.file.read(&mut bytes) {
+                    Ok(8) => {
+                        if let Some(event) = JoystickEvent::parse(bytes) {
+                            self.state.apply(event);
+                        }
+                    }
+                    Ok(_) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+    }
+
+    fn open_nonblocking(path: &Path) -> io::Result<File> {
+        OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(path)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod controllers {
+    use std::collections::BTreeMap;
+
+    use gilrs::{Axis, Button, EventType, Gilrs};
+
+    use super::{GamepadState, JoystickEvent};
+
+    /// Gamepads gilrs knows about, by id, which is the order they connected.
+    pub struct Controllers {
+        /// `None` if gilrs couldn't start, in which case there are no gamepads.
+        gilrs: Option<Gilrs>,
+        states: BTreeMap<usize, GamepadState>,
+    }
+
+    impl Controllers {
+        pub fn new() -> Controllers {
+            Controllers {
+                gilrs: Gilrs::new().ok(),
+                states: BTreeMap::new(),
+            }
+        }
+
+        pub fn states(&self) -> impl Iterator<Item = &GamepadState> {
+            self.states.values()
+        }
+
+        /// Apply every waiting event and pick up new gamepads.
+        pub fn update(&mut self) {
+            let gilrs = match &mut self.gilrs {
+                Some(gilrs) => gilrs,
+                None => return,
+            };
+            while let Some(event) = gilrs.next_event() {
+                let id: usize = event.id.into();
+                if let EventType::Disconnected = event.event {
+                    self.states.remove(&id);
+                    continue;
+                }
+                let state = self.states.entry(id).or_default();
+                if let Some(event) = joystick_event(event.event) {
+                    state.apply(event);
+                }
+            }
+            // Gamepads connected before we started send no event
+            for (id, _) in gilrs.gamepads() {
+                self.states.entry(id.into()).or_default();
+            }
+        }
+    }
+
+    /// The joystick API event for a gilrs one, numbered like the xpad driver.
+    fn joystick_event(event: EventType) -> Option<JoystickEvent> {
+        let (button, pressed) = match event {
+            EventType::ButtonPressed(button, _) => (button, true),
+            EventType::ButtonReleased(button, _) => (button, false),
+            EventType::AxisChanged(axis, value, _) => {
+                // gilrs has up as positive, the joystick API down
+                let (number, value) = match axis {
+                    Axis::LeftStickX => (0, value),
+                    Axis::LeftStickY => (1, -value),
+                    Axis::DPadX => (6, value),
+                    Axis::DPadY => (7, -value),
+                    _ => return None,
+                };
+                let value = (value * i16::MAX as f32) as i16;
+                return Some(JoystickEvent::Axis { number, value });
+            }
+            _ => return None,
+        };
+        let axis = |number, value: i16| JoystickEvent::Axis {
+            number,
+            value: if pressed { value } else { 0 },
+        };
+        let number = match button {
+            Button::South => 0,
+            Button::East => 1,
+            Button::West => 2,
+            Button::North => 3,
+            Button::Select => 6,
+            Button::Start => 7,
+            Button::DPadUp => return Some(axis(7, -i16::MAX)),
+            Button::DPadDown => return Some(axis(7, i16::MAX)),
+            Button::DPadLeft => return Some(axis(6, -i16::MAX)),
+            Button::DPadRight => return Some(axis(6, i16::MAX)),
+            _ => return None,
+        };
+        Some(JoystickEvent::Button { number, pressed })
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::fs::{read_to_string, write};
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use winit::event::VirtualKeyCode;
+
+use crate::database::sha1_hex;
+
+/// The key configuration frontends look for in the working directory.
+pub const CONFIG_FILE: &str = "keys.json";
+
+/// Names of the built-in layouts.
+pub const LAYOUTS: [&str; 4] = ["qwerty", "azerty", "dvorak", "numpad"];
+
+/// The COSMAC VIP's hex keypad, row by row.
+const HEX_KEYPAD: [u8; 16] = [
+    0x1, 0x2, 0x3, 0xc, //
+    0x4, 0x5, 0x6, 0xd, //
+    0x7, 0x8, 0x9, 0xe, //
+    0xa, 0x0, 0xb, 0xf, //
+];
+
+/// Which keyboard keys press which keypad keys.
+///
+/// Keys are named by the character on them, such as `q`, `4` or `;`, or by
+/// name: `space`, `up`, `down`, `left`, `right`, `numpad0` to `numpad9`,
+/// `numpadadd`, `numpadsubtract`, `numpadmultiply`, `numpaddivide`,
+/// `numpaddecimal` and `numpadenter`. Names are case-insensitive. A keypad key
+/// can have several keyboard keys.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyMap {
+    keys: HashMap<String, u8>,
+}
+
+impl Default for KeyMap {
+    fn default() -> KeyMap {
+        KeyMap::qwerty()
+    }
+}
+
+impl KeyMap {
+    /// No keys at all.
+    pub fn empty() -> KeyMap {
+        KeyMap {
+            keys: HashMap::new(),
+        }
+    }
+
+    /// Lay the hex keypad over a 4x4 block of keyboard keys, given row by row.
+    fn grid(keys: [&str; 16]) -> KeyMap {
+        let mut map = KeyMap::empty();
+        for (name, key) in keys.iter().zip(HEX_KEYPAD.iter()) {
+            map.bind(name, *key);
+        }
+        map
+    }
+
+    /// The hex keypad on 1234, QWER, ASDF and ZXCV.
+    pub fn qwerty() -> KeyMap {
+        KeyMap::grid([
+            "1", "2", "3", "4", "q", "w", "e", "r", "a", "s", "d", "f", "z", "x", "c", "v",
+        ])
+    }
+
+    /// The same keys as `qwerty` on an AZERTY keyboard.
+    pub fn azerty() -> KeyMap {
+        KeyMap::grid([
+            "1", "2", "3", "4", "a", "z", "e", "r", "q", "s", "d", "f", "w", "x", "c", "v",
+        ])
+    }
+
+    /// The same keys as `qwerty` on a Dvorak keyboard.
+    pub fn dvorak() -> KeyMap {
+        KeyMap::grid([
+            "1", "2", "3", "4", "'", ",", ".", "p", "a", "o", "e", "u", ";", "q", "j", "k",
+        ])
+    }
+
+    /// Digits on the numpad keys of the same name, and A to F on the keys around them.
+    pub fn numpad() -> KeyMap {
+        let mut map = KeyMap::empty();
+        for digit in 0..10 {
+            map.bind(&format!("numpad{}", digit), digit);
+        }
+        map.bind("numpaddecimal", 0xa);
+        map.bind("numpadenter", 0xb);
+        map.bind("numpaddivide", 0xc);
+        map.bind("numpadmultiply", 0xd);
+        map.bind("numpadsubtract", 0xe);
+        map.bind("numpadadd", 0xf);
+        map
+    }
+
+    /// A built-in layout by name.
+    pub fn layout(name: &str) -> Option<KeyMap> {
+        match name.to_ascii_lowercase().as_str() {
+            "qwerty" => Some(KeyMap::qwerty()),
+            "azerty" => Some(KeyMap::azerty()),
+            "dvorak" => Some(KeyMap::dvorak()),
+            "numpad" => Some(KeyMap::numpad()),
+            _ => None,
+        }
+    }
+
+    /// Make a keyboard key press a keypad key, replacing what it did before.
+    pub fn bind(&mut self, name: &str, key: u8) {
+        self.keys.insert(name.to_lowercase(), key & 0xf);
+    }
+
+    pub fn unbind(&mut self, name: &str) {
+        self.keys.remove(&name.to_lowercase());
+    }
+
+    /// The keypad key a keyboard key presses.
+    pub fn keypad_key(&self, name: &str) -> Option<u8> {
+        self.keys.get(&name.to_lowercase()).copied()
+    }
+
+    /// Keyboard key names and the keypad keys they press, in no particular order.
+    pub fn bindings(&self) -> impl Iterator<Item = (&str, u8)> {
+        self.keys.iter().map(|(name, key)| (name.as_str(), *key))
+    }
+}
+
+/// The key configuration file: a layout, changes to it, and changes for
+/// particular ROMs.
+///
+/// ```json
+/// {
+///     "layout": "azerty",
+///     "keys": { "space": 5 },
+///     "roms": { "<sha1 of the ROM>": { "up": 1, "down": 4 } },
+///     "gamepads": { "<sha1 of the ROM>": { "a": 5, "player2Up": 12 } }
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyConfig {
+    /// One of `LAYOUTS`, qwerty if not given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout: Option<String>,
+    /// Keyboard keys to keypad keys, on top of the layout.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub keys: HashMap<String, u8>,
+    /// Keyboard keys to keypad keys for ROMs by SHA-1, applied last.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub roms: HashMap<String, HashMap<String, u8>>,
+    /// Gamepad actions to keypad keys for ROMs by SHA-1, named like the
+    /// database's `keys`, which they override.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub gamepads: HashMap<String, HashMap<String, u8>>,
+}
+
+impl KeyConfig {
+    pub fn load(path: &Path) -> io::Result<KeyConfig> {
+        let config: KeyConfig = serde_json::from_str(&read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if let Some(layout) = &config.layout {
+            if KeyMap::layout(layout).is_none() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unknown keyboard layout {:?}", layout),
+                ));
+            }
+        }
+        Ok(config)
+    }
+
+    /// Load `CONFIG_FILE` if there is one.
+    pub fn load_default() -> io::Result<KeyConfig> {
+        let path = Path::new(CONFIG_FILE);
+        if path.exists() {
+            KeyConfig::load(path)
+        } else {
+            Ok(KeyConfig::default())
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        write(path, serde_json::to_string_pretty(self)? + "\n")
+    }
+
+    /// Remember a binding for one ROM.
+    pub fn bind_for_rom(&mut self, rom: &[u8], name: &str, key: u8) {
+        self.roms
+            .entry(sha1_hex(rom))
+            .or_default()
+            .insert(name.to_lowercase(), key & 0xf);
+    }
+
+    /// The keys to use for `rom`.
+    pub fn key_map(&self, rom: &[u8]) -> KeyMap {
+        let mut map = self
+            .layout
+            .as_deref()
+            .and_then(KeyMap::layout)
+            .unwrap_or_default();
+        for (name, key) in &self.keys {
+            map.bind(name, *key);
+        }
+        if let Some(keys) = self.roms.get(&sha1_hex(rom)) {
+            for (name, key) in keys {
+                map.bind(name, *key);
+            }
+        }
+        map
+    }
+
+    /// Gamepad actions for `rom`: `defaults`, usually from the database, with
+    /// this ROM's `gamepads` entry on top.
+    pub fn gamepad_actions(
+        &self,
+        rom: &[u8],
+        defaults: &HashMap<String, u8>,
+    ) -> HashMap<String, u8> {
+        let mut actions = defaults.clone();
+        if let Some(overrides) = self.gamepads.get(&sha1_hex(rom)) {
+            actions.extend(
+                overrides
+                    .iter()
+                    .map(|(name, key)| (name.clone(), key & 0xf)),
+            );
+        }
+        actions
+    }
+}
+
+/// The winit key code for a key name, if there is one.
+pub fn virtual_key_code(name: &str) -> Option<VirtualKeyCode> {
+    use VirtualKeyCode::*;
+    const DIGITS: [VirtualKeyCode; 10] =
+        [Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9];
+    const LETTERS: [VirtualKeyCode; 26] = [
+        A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    ];
+    const NUMPAD: [VirtualKeyCode; 10] = [
+        Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+    ];
+    let name = name.to_lowercase();
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return match c {
+            '0'..='9' => Some(DIGITS[c as usize - '0' as usize]),
+            'a'..='z' => Some(LETTERS[c as usize - 'a' as usize]),
+            '\'' => Some(Apostrophe),
+            ',' => Some(Comma),
+            '.' => Some(Period),
+            ';' => Some(Semicolon),
+            '/' => Some(Slash),
+            '-' => Some(Minus),
+            '=' => Some(Equals),
+            '[' => Some(LBracket),
+            ']' => Some(RBracket),
+            _ => None,
+        };
+    }
+    if let Some(digit) = name
+        .strip_prefix("numpad")
+        .and_then(|d| d.parse::<usize>().ok())
+    {
+        return NUMPAD.get(digit).copied();
+    }
+    match name.as_str() {
+        "space" => Some(Space),
+        "up" => Some(Up),
+        "down" => Some(Down),
+        "left" => Some(Left),
+        "right" => Some(Right),
+        "numpadadd" => Some(NumpadAdd),
+        "numpadsubtract" => Some(NumpadSubtract),
+        "numpadmultiply" => Some(NumpadMultiply),
+        "numpaddivide" => Some(NumpadDivide),
+        "numpaddecimal" => Some(NumpadDecimal),
+        "numpadenter" => Some(NumpadEnter),
+        _ => None,
+    }
+}
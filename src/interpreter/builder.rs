@@ -0,0 +1,129 @@
+use super::interpreter::Interpreter;
+use super::memory::Memory;
+use super::quirks::Quirks;
+use super::timing::Timing;
+
+/// Builds an interpreter in a known state, mainly for tests.
+///
+/// ```
+/// use hoyer_chip_8::interpreter::interpreter::Interpreter;
+///
+/// let mut interpreter = Interpreter::builder()
+///     .program(&[0x8014])
+///     .registers(&[0xff, 0x01])
+///     .build();
+/// interpreter.update();
+/// assert_eq!(interpreter.registers[0], 0x00);
+/// assert_eq!(interpreter.registers[0xf], 1);
+/// ```
+pub struct InterpreterBuilder {
+    interpreter: Interpreter,
+}
+
+impl Interpreter {
+    pub fn builder() -> InterpreterBuilder {
+        let mut interpreter = Interpreter::blank();
+        interpreter.seed(0);
+        InterpreterBuilder { interpreter }
+    }
+}
+
+impl InterpreterBuilder {
+    /// Load a ROM at 0x200. Panics if it doesn't fit.
+    pub fn rom(mut self, bytes: &[u8]) -> InterpreterBuilder {
+        if let Err(e) = self.interpreter.load_bytes(bytes.to_vec()) {
+            panic!("{}", e);
+        }
+        self
+    }
+
+    /// Load opcodes as a ROM at 0x200.
+    pub fn program(self, opcodes: &[u16]) -> InterpreterBuilder {
+        let bytes: Vec<u8> = opcodes.iter().flat_map(|op| op.to_be_bytes()).collect();
+        self.rom(&bytes)
+    }
+
+    /// Set V0, V1, ... to `values`.
+    pub fn registers(mut self, values: &[u8]) -> InterpreterBuilder {
+        self.interpreter.registers[..values.len()].copy_from_slice(values);
+        self
+    }
+
+    pub fn register(mut self, x: usize, value: u8) -> InterpreterBuilder {
+        self.interpreter.registers[x] = value;
+        self
+    }
+
+    pub fn memory_at(mut self, address: usize, bytes: &[u8]) -> InterpreterBuilder {
+        self.interpreter.memory.poke_range(address, bytes);
+        self
+    }
+
+    /// Use different memory, such as `Ram` of another size or with an observer.
+    pub fn memory(mut self, memory: Box<dyn Memory>) -> InterpreterBuilder {
+        self.interpreter.replace_memory(memory);
+        self
+    }
+
+    pub fn index(mut self, index: u16) -> InterpreterBuilder {
+        self.interpreter.index = index;
+        self
+    }
+
+    pub fn program_counter(mut self, address: u16) -> InterpreterBuilder {
+        self.interpreter.program_counter = address;
+        self
+    }
+
+    /// Push return addresses onto the stack, bottom first.
+    pub fn stack(mut self, addresses: &[u16]) -> InterpreterBuilder {
+        for address in addresses {
+            self.interpreter.stack[self.interpreter.stack_pointer] = *address;
+            self.interpreter.stack_pointer += 1;
+        }
+        self
+    }
+
+    /// Hold down keypad keys.
+    pub fn keys(mut self, keys: &[u8]) -> InterpreterBuilder {
+        for key in keys {
+            self.interpreter.keypad[*key as usize] = true;
+        }
+        self
+    }
+
+    pub fn delay_timer(mut self, value: u8) -> InterpreterBuilder {
+        self.interpreter.delay_timer = value;
+        self
+    }
+
+    pub fn sound_timer(mut self, value: u8) -> InterpreterBuilder {
+        self.interpreter.sound_timer = value;
+        self
+    }
+
+    pub fn video_output(mut self, video_output: [u64; 32]) -> InterpreterBuilder {
+        self.interpreter.video_output = video_output;
+        self
+    }
+
+    pub fn quirks(mut self, quirks: Quirks) -> InterpreterBuilder {
+        self.interpreter.quirks = quirks;
+        self
+    }
+
+    pub fn timing(mut self, timing: Timing) -> InterpreterBuilder {
+        self.interpreter.timing = timing;
+        self
+    }
+
+    /// Seed the random number generator. Built interpreters are seeded with 0 by default.
+    pub fn seed(mut self, seed: u64) -> InterpreterBuilder {
+        self.interpreter.seed(seed);
+        self
+    }
+
+    pub fn build(self) -> Interpreter {
+        self.interpreter
+    }
+}
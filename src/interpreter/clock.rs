@@ -0,0 +1,156 @@
+use super::interpreter::Interpreter;
+
+/// Speeds `Clock::faster` and `Clock::slower` step through, as multiples of
+/// the normal 60 frames a second.
+pub const SPEEDS: [f64; 7] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0];
+
+/// The speed while turbo is held, unless changed.
+pub const DEFAULT_TURBO_SPEED: f64 = 8.0;
+
+/// Decides how many frames to run for each tick of the frontend's 60 Hz timer:
+/// none while paused, fewer or more at other speeds, and single frames or
+/// instructions on request.
+///
+/// Speeding up runs more whole frames, timers and all, so games run faster
+/// the same way they would on a faster clock.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Clock {
+    speed: f64,
+    /// The speed while `turbo` is on.
+    pub turbo_speed: f64,
+    /// Run at `turbo_speed`, such as while a key is held.
+    pub turbo: bool,
+    paused: bool,
+    /// Frames owed from slow speeds, between 0 and 1.
+    progress: f64,
+    /// A frame has been started but not finished, by stepping instructions.
+    mid_frame: bool,
+    /// Frames and instructions asked for while paused.
+    frame_steps: u32,
+    instruction_steps: u32,
+}
+
+impl Default for Clock {
+    fn default() -> Clock {
+        Clock {
+            speed: 1.0,
+            turbo_speed: DEFAULT_TURBO_SPEED,
+            turbo: false,
+            paused: false,
+            progress: 0.0,
+            mid_frame: false,
+            frame_steps: 0,
+            instruction_steps: 0,
+        }
+    }
+}
+
+impl Clock {
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Set the speed, between the slowest and fastest of `SPEEDS`.
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed.clamp(SPEEDS[0], SPEEDS[SPEEDS.len() - 1]);
+    }
+
+    /// Step up to the next of `SPEEDS`, returning the new speed.
+    pub fn faster(&mut self) -> f64 {
+        let next = SPEEDS.iter().find(|speed| **speed > self.speed);
+        self.set_speed(*next.unwrap_or(&self.speed));
+        self.speed
+    }
+
+    /// Step down to the next of `SPEEDS`, returning the new speed.
+    pub fn slower(&mut self) -> f64 {
+        let next = SPEEDS.iter().rev().find(|speed| **speed < self.speed);
+        self.set_speed(*next.unwrap_or(&self.speed));
+        self.speed
+    }
+
+    /// The speed frames are running at, with turbo.
+    pub fn effective_speed(&self) -> f64 {
+        if self.turbo {
+            self.turbo_speed
+        } else {
+            self.speed
+        }
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.progress = 0.0;
+    }
+
+    /// Run one more frame on the next tick, pausing first if running.
+    pub fn step_frame(&mut self) {
+        self.set_paused(true);
+        self.frame_steps += 1;
+    }
+
+    /// Run one more instruction on the next tick, pausing first if running.
+    pub fn step_instruction(&mut self) {
+        self.set_paused(true);
+        self.instruction_steps += 1;
+    }
+
+    /// Handle one tick of the frontend's 60 Hz timer, returning the number of
+    /// frames finished.
+    pub fn tick(&mut self, interpreter: &mut Interpreter) -> u32 {
+        let mut frames = 0;
+        if self.paused {
+            for _ in 0..std::mem::take(&mut self.instruction_steps) {
+                frames += self.run_instruction(interpreter);
+            }
+            for _ in 0..std::mem::take(&mut self.frame_steps) {
+                self.run_frame(interpreter);
+                frames += 1;
+            }
+            return frames;
+        }
+        self.progress += self.effective_speed();
+        while self.progress >= 1.0 {
+            self.progress -= 1.0;
+            self.run_frame(interpreter);
+            frames += 1;
+        }
+        frames
+    }
+
+    /// Run the rest of the current frame, or a whole one, and its vertical
+    /// blank.
+    pub fn run_frame(&mut self, interpreter: &mut Interpreter) {
+        if !std::mem::take(&mut self.mid_frame) {
+            interpreter.start_frame();
+        }
+        while interpreter.update_within_frame() {}
+        interpreter.vblank();
+    }
+
+    /// Run the next instruction, moving on to the next frame first if this
+    /// one has no time left or is waiting for the vertical blank. Returns the
+    /// number of frames that finished, 0 or 1.
+    pub fn run_instruction(&mut self, interpreter: &mut Interpreter) -> u32 {
+        if !self.mid_frame {
+            interpreter.start_frame();
+            self.mid_frame = true;
+        }
+        if interpreter.update_within_frame() {
+            return 0;
+        }
+        interpreter.vblank();
+        interpreter.start_frame();
+        interpreter.update_within_frame();
+        1
+    }
+}
+
+/// Write a speed like "0.25x" or "2x".
+pub fn format_speed(speed: f64) -> String {
+    format!("{}x", speed)
+}
@@ -0,0 +1,59 @@
+use serde::Serialize;
+
+/// A program writing over one of its own instructions after running it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct SelfModification {
+    /// The byte overwritten.
+    pub address: usize,
+    /// The instruction that did it.
+    pub program_counter: u16,
+    pub old: u8,
+    pub new: u8,
+}
+
+/// Which bytes of memory have been run as code and which written as data, to
+/// find self-modifying code.
+#[derive(Clone, Debug, Default)]
+pub struct CodeMap {
+    pub executed: Vec<bool>,
+    pub written: Vec<bool>,
+    /// The first change to each executed byte, oldest first. Later changes to
+    /// the same byte aren't reported again.
+    pub modifications: Vec<SelfModification>,
+    modified: Vec<bool>,
+}
+
+impl CodeMap {
+    pub fn new(size: usize) -> CodeMap {
+        CodeMap {
+            executed: vec![false; size],
+            written: vec![false; size],
+            modifications: Vec::new(),
+            modified: vec![false; size],
+        }
+    }
+
+    pub fn execute(&mut self, address: usize) {
+        self.executed[address] = true;
+    }
+
+    /// Record the instruction at `program_counter` writing `new` over `old`.
+    /// Writing the same value back isn't a modification.
+    pub fn write(&mut self, address: usize, program_counter: u16, old: u8, new: u8) {
+        self.written[address] = true;
+        if self.executed[address] && old != new && !self.modified[address] {
+            self.modified[address] = true;
+            self.modifications.push(SelfModification {
+                address,
+                program_counter,
+                old,
+                new,
+            });
+        }
+    }
+
+    /// Whether a byte has been both run and written.
+    pub fn is_self_modified(&self, address: usize) -> bool {
+        self.modified[address]
+    }
+}
@@ -1,18 +1,34 @@
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
+use super::fault::Fault;
 use super::interpreter::Interpreter;
+use super::memory::Access;
+use super::sprite::blit;
+use super::state::CpuState;
+use crate::constants::{WINDOW_HEIGHT, WINDOW_WIDTH};
 impl Interpreter {
     /// 00E0 - CLS
     ///
     /// Clear the display.
     pub fn clear_display(&mut self) {
-        self.video_output = [0; 32];
+        let before = [self.video_output, self.second_plane];
+        if self.selected_planes & 1 != 0 {
+            self.video_output = [0; 32];
+        }
+        if self.selected_planes & 2 != 0 {
+            self.second_plane = [0; 32];
+        }
+        self.mark_dirty(&before);
     }
 
     /// 00EE - RET
     ///
     /// Return from a subroutine.
     pub fn return_from_subroutine(&mut self) {
+        if self.stack_pointer == 0 {
+            self.fault = Some(Fault::StackUnderflow);
+            return;
+        }
         self.stack_pointer -= 1;
         self.program_counter = self.stack[self.stack_pointer];
     }
@@ -28,6 +44,10 @@ impl Interpreter {
     ///
     /// Call subroutine at nnn.
     pub fn call_subroutine(&mut self, nnn: u16) {
+        if self.stack_pointer == self.stack.len() {
+            self.fault = Some(Fault::StackOverflow);
+            return;
+        }
         self.stack[self.stack_pointer] = self.program_counter;
         self.stack_pointer += 1;
         self.program_counter = nnn;
@@ -38,7 +58,7 @@ impl Interpreter {
     /// Skip next instruction if Vx = nn.
     pub fn skip_if_vx_equal_nn(&mut self, vx: usize, nn: u8) {
         if self.registers[vx] == nn {
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
         }
     }
 
@@ -47,7 +67,7 @@ impl Interpreter {
     /// Skip next instruction if Vx != nn.
     pub fn skip_if_vx_not_equal_nn(&mut self, vx: usize, nn: u8) {
         if self.registers[vx] != nn {
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
         }
     }
 
@@ -56,7 +76,7 @@ impl Interpreter {
     /// Skip next instruction if Vx = Vy.
     pub fn skip_if_vx_equal_vy(&mut self, vx: usize, vy: usize) {
         if self.registers[vx] == self.registers[vy] {
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
         }
     }
 
@@ -87,6 +107,9 @@ impl Interpreter {
     /// Set Vx = Vx OR Vy.
     pub fn vx_or_vy(&mut self, vx: usize, vy: usize) {
         self.registers[vx] |= self.registers[vy];
+        if self.quirks.logic {
+            self.registers[0xf] = 0;
+        }
     }
 
     /// 8xy2 - AND Vx, Vy
@@ -94,6 +117,9 @@ impl Interpreter {
     /// Set Vx = Vx AND Vy.
     pub fn vx_and_vy(&mut self, vx: usize, vy: usize) {
         self.registers[vx] &= self.registers[vy];
+        if self.quirks.logic {
+            self.registers[0xf] = 0;
+        }
     }
 
     /// 8xy3 - XOR Vx, Vy
@@ -101,55 +127,57 @@ impl Interpreter {
     /// Set Vx = Vx XOR Vy.
     pub fn vx_xor_vy(&mut self, vx: usize, vy: usize) {
         self.registers[vx] ^= self.registers[vy];
+        if self.quirks.logic {
+            self.registers[0xf] = 0;
+        }
     }
 
     /// 8xy4 - ADD Vx, Vy
     ///
     /// Set Vx = Vx + Vy, set Vf = carry.
     pub fn vx_add_vy(&mut self, vx: usize, vy: usize) {
-        let sum = self.registers[vx] as u16 + self.registers[vy] as u16;
-        self.registers[0xf] = if sum > 255 { 1 } else { 0 };
-        self.registers[vx] = (sum & 0xff) as u8;
+        let (sum, carry) = self.registers[vx].overflowing_add(self.registers[vy]);
+        self.set_vx_and_flag(vx, sum, carry as u8);
     }
 
     /// 8xy5 - SUB Vx, Vy
     ///
     /// Set Vx = Vx - Vy, set Vf = NOT borrow.
     pub fn vx_sub_vy(&mut self, vx: usize, vy: usize) {
-        self.registers[0xf] = if self.registers[vx] > self.registers[vy] {
-            1
-        } else {
-            0
-        };
-        self.registers[vx] = self.registers[vx].wrapping_sub(self.registers[vy]);
+        let (difference, borrow) = self.registers[vx].overflowing_sub(self.registers[vy]);
+        self.set_vx_and_flag(vx, difference, !borrow as u8);
     }
 
     /// 8xy6 - SHR Vx {, Vy}
     ///
-    /// Set Vx = Vx SHR 1.
-    pub fn vx_shift_right(&mut self, vx: usize) {
-        self.registers[0xf] = self.registers[vx] & 1;
-        self.registers[vx] >>= 1;
+    /// Set Vx = Vy SHR 1, or Vx = Vx SHR 1 with the shift quirk. Set Vf = the bit shifted out.
+    pub fn vx_shift_right(&mut self, vx: usize, vy: usize) {
+        let value = self.registers[if self.quirks.shift { vx } else { vy }];
+        self.set_vx_and_flag(vx, value >> 1, value & 1);
     }
 
     /// 8xy7 - SUBN Vx, Vy
     ///
     /// Set Vx = Vy - Vx, set Vf = NOT borrow.
     pub fn vx_subn_vy(&mut self, vx: usize, vy: usize) {
-        self.registers[0xf] = if self.registers[vx] < self.registers[vy] {
-            1
-        } else {
-            0
-        };
-        self.registers[vx] = self.registers[vy].wrapping_sub(self.registers[vx]);
+        let (difference, borrow) = self.registers[vy].overflowing_sub(self.registers[vx]);
+        self.set_vx_and_flag(vx, difference, !borrow as u8);
     }
 
     /// 8xyE - SHL Vx {, Vy}
     ///
-    /// Set Vx = Vx SHL 1.
-    pub fn vx_shift_left(&mut self, vx: usize) {
-        self.registers[0xf] = (self.registers[vx] & 0b10000000) >> 7;
-        self.registers[vx] <<= 1;
+    /// Set Vx = Vy SHL 1, or Vx = Vx SHL 1 with the shift quirk. Set Vf = the bit shifted out.
+    pub fn vx_shift_left(&mut self, vx: usize, vy: usize) {
+        let value = self.registers[if self.quirks.shift { vx } else { vy }];
+        self.set_vx_and_flag(vx, value << 1, value >> 7);
+    }
+
+    /// Write the result of an 8xyN instruction, then its flag.
+    ///
+    /// The flag is written last so that it wins when x is F, like on the COSMAC VIP.
+    fn set_vx_and_flag(&mut self, vx: usize, value: u8, flag: u8) {
+        self.registers[vx] = value;
+        self.registers[0xf] = flag;
     }
 
     /// 9xy0 - SNE Vx, Vy
@@ -157,7 +185,7 @@ impl Interpreter {
     /// Skip next instruction if Vx != Vy.
     pub fn skip_if_vx_not_equal_vy(&mut self, vx: usize, vy: usize) {
         if self.registers[vx] != self.registers[vy] {
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
         }
     }
 
@@ -170,37 +198,68 @@ impl Interpreter {
 
     /// Bnnn - JP V0, addr
     ///
-    /// Jump to location nnn + V0.
-    pub fn jump_with_offset(&mut self, nnn: u16) {
-        self.program_counter = self.registers[0] as u16 + nnn;
+    /// Jump to location nnn + V0, or xnn + Vx with the jump quirk.
+    pub fn jump_with_offset(&mut self, vx: usize, nnn: u16) {
+        let offset = if self.quirks.jump { vx } else { 0 };
+        self.program_counter = self.registers[offset] as u16 + nnn;
     }
 
     /// Cxnn - RND Vx, byte
     ///
     /// Set Vx = random byte AND nn.
     pub fn vx_set_rand_and_nn(&mut self, vx: usize, nn: u8) {
-        self.registers[vx] = thread_rng().gen::<u8>() & nn;
+        self.registers[vx] = self.rng.gen::<u8>() & nn;
     }
 
     /// Dxyn - DRW Vx, Vy, nibble
     ///
     /// Display n-byte sprite starting at memory location I at (Vx, Vy), set Vf = collision.
+    /// With large sprites, Dxy0 displays a 16x16 sprite of 32 bytes. Each selected plane
+    /// takes its own sprite data, one after the other.
+    ///
+    /// With the vblank quirk the CPU then stops until the next call to `vblank`.
     pub fn display_sprite(&mut self, vx: usize, vy: usize, n: u8) {
-        let x = self.registers[vx] % 64;
-        let y = self.registers[vy] % 32;
-
-        for byte in 0..n {
-            let sprite = self.memory[(self.index + byte as u16) as usize];
-            for bit in 0..8 {
-                let color = (sprite & (0x80 >> bit)) as u64;
-                self.registers[0xf] |= ((self.video_output[(y as usize + byte as usize) % 32]
-                    & (color << x % 64))
-                    >> x % 64) as u8
-                    >> 7 - bit;
-
-                self.video_output[(y as usize + byte as usize) % 32] ^=
-                    (color >> 7 - bit) << (x + bit) % 64;
+        let x = self.registers[vx] as u32 % WINDOW_WIDTH;
+        let y = self.registers[vy] as u32 % WINDOW_HEIGHT;
+        let (width, height) = match n {
+            0 if self.quirks.large_sprites => (16, 16),
+            _ => (8, n as usize),
+        };
+        let bytes_per_plane = height * width as usize / 8;
+        let planes = self.selected_planes.count_ones() as usize;
+        if !self.check_memory(self.index as usize, bytes_per_plane * planes) {
+            return;
+        }
+
+        let before = [self.video_output, self.second_plane];
+        let mut collision = false;
+        let mut address = self.index as usize;
+        for plane in 0..2 {
+            if self.selected_planes & (1 << plane) == 0 {
+                continue;
             }
+            let data: Vec<u8> = (address..address + bytes_per_plane)
+                .map(|address| self.memory.read(address, Access::Read))
+                .collect();
+            let rows: Vec<u16> = if width == 16 {
+                data.chunks(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                    .collect()
+            } else {
+                data.iter().map(|byte| *byte as u16).collect()
+            };
+            let target = if plane == 0 {
+                &mut self.video_output
+            } else {
+                &mut self.second_plane
+            };
+            collision |= blit(target, x, y, &rows, width, self.quirks.wrap);
+            address += bytes_per_plane;
+        }
+        self.mark_dirty(&before);
+        self.registers[0xf] = collision as u8;
+        if self.quirks.vblank {
+            self.state = CpuState::WaitingForVblank;
         }
     }
 
@@ -208,8 +267,8 @@ impl Interpreter {
     ///
     /// Skip next instruction if key with the value of Vx is pressed.
     pub fn skip_if_key(&mut self, vx: usize) {
-        if self.keypad[self.registers[vx] as usize] {
-            self.program_counter += 2;
+        if self.keypad[self.registers[vx] as usize & 0xf] {
+            self.program_counter = self.program_counter.wrapping_add(2);
         }
     }
 
@@ -217,11 +276,18 @@ impl Interpreter {
     ///
     /// Skip next instruction if key with the value of Vx is not pressed.
     pub fn skip_if_not_key(&mut self, vx: usize) {
-        if !self.keypad[self.registers[vx] as usize] {
-            self.program_counter += 2;
+        if !self.keypad[self.registers[vx] as usize & 0xf] {
+            self.program_counter = self.program_counter.wrapping_add(2);
         }
     }
 
+    /// Fn01 - PLANE n
+    ///
+    /// Select the display planes drawn to and cleared (XO-CHIP).
+    pub fn select_planes(&mut self, n: u8) {
+        self.selected_planes = n & 0b11;
+    }
+
     /// fx07 - LD Vx, DT
     ///
     /// Set Vx = delay timer value.
@@ -232,17 +298,15 @@ impl Interpreter {
     /// fx0A - LD Vx, K
     ///
     /// Wait for a key press, store the value of the key in Vx.
+    ///
+    /// Only keys pressed after the instruction starts count. The CPU stops in
+    /// `CpuState::WaitingForKey` while the timers keep running, see `update_key_wait`.
     pub fn wait_for_key(&mut self, vx: usize) {
-        let mut found_key = false;
-        for key in 0..16 {
-            if self.keypad[key] {
-                found_key = true;
-                self.registers[vx] = key as u8;
-            }
-        }
-        if !found_key {
-            self.program_counter -= 2;
-        }
+        self.state = CpuState::WaitingForKey {
+            vx,
+            held: self.keypad,
+            pressed: None,
+        };
     }
 
     /// fx15 - LD DT, Vx
@@ -263,7 +327,7 @@ impl Interpreter {
     ///
     /// Set I = I + Vx.
     pub fn index_add_vx(&mut self, vx: usize) {
-        self.index += self.registers[vx] as u16;
+        self.index = self.index.wrapping_add(self.registers[vx] as u16);
     }
 
     /// fx29 - LD f, Vx
@@ -277,9 +341,12 @@ impl Interpreter {
     ///
     /// Store BCD representation of Vx in memory locations I, I+1, and I+2.
     pub fn index_set_decimal(&mut self, vx: usize) {
+        if !self.check_memory(self.index as usize, 3) {
+            return;
+        }
         let mut value = self.registers[vx];
         for i in 0..3 {
-            self.memory[self.index as usize + 2 - i] = value % 10;
+            self.store(self.index as usize + 2 - i, value % 10);
             value /= 10;
         }
     }
@@ -288,17 +355,38 @@ impl Interpreter {
     ///
     /// Store registers V0 through Vx in memory starting at location I.
     pub fn write_memory(&mut self, vx: usize) {
+        if !self.check_memory(self.index as usize, vx + 1) {
+            return;
+        }
         for i in 0..(vx + 1) {
-            self.memory[self.index as usize + i] = self.registers[i];
+            self.store(self.index as usize + i, self.registers[i]);
         }
+        self.increment_index_after_memory(vx);
     }
 
     /// fx65 - LD Vx, [I]
     ///
     /// Read registers V0 through Vx from memory starting at location I.
     pub fn load_memory(&mut self, vx: usize) {
+        if !self.check_memory(self.index as usize, vx + 1) {
+            return;
+        }
         for i in 0..(vx + 1) {
-            self.registers[i] = self.memory[self.index as usize + i];
+            self.registers[i] = self.memory.read(self.index as usize + i, Access::Read);
         }
+        self.increment_index_after_memory(vx);
+    }
+
+    /// Advance I after fx55/fx65 according to the memory quirks.
+    fn increment_index_after_memory(&mut self, vx: usize) {
+        if self.quirks.memory_leave_i_unchanged {
+            return;
+        }
+        let increment = if self.quirks.memory_increment_by_x {
+            vx as u16
+        } else {
+            vx as u16 + 1
+        };
+        self.index = self.index.wrapping_add(increment);
     }
 }
@@ -0,0 +1,32 @@
+use std::fmt;
+
+/// An error that stops a program from continuing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// The opcode at the program counter isn't a CHIP-8 instruction.
+    UnknownOpcode(u16),
+    /// 2nnn was called with a full stack.
+    StackOverflow,
+    /// 00EE was called with an empty stack.
+    StackUnderflow,
+    /// The program counter ran past the end of memory.
+    ProgramCounterOutOfBounds(u16),
+    /// An instruction tried to access memory past the end of memory.
+    MemoryOutOfBounds(usize),
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::UnknownOpcode(opcode) => write!(f, "unknown opcode {:04X}", opcode),
+            Fault::StackOverflow => write!(f, "stack overflow"),
+            Fault::StackUnderflow => write!(f, "stack underflow"),
+            Fault::ProgramCounterOutOfBounds(address) => {
+                write!(f, "program counter out of bounds at {:04X}", address)
+            }
+            Fault::MemoryOutOfBounds(address) => {
+                write!(f, "memory access out of bounds at {:04X}", address)
+            }
+        }
+    }
+}
@@ -1,12 +1,24 @@
-use std::fs::read;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
 
-use winit::event::VirtualKeyCode;
 use winit_input_helper::WinitInputHelper;
 
+use super::code_map::CodeMap;
+use super::fault::Fault;
+use super::memory::{Access, Memory, Ram, FONT_START, MEMORY_SIZE, PROGRAM_START};
+use super::quirks::Quirks;
+use super::state::CpuState;
+use super::timing::Timing;
 use crate::constants::FONTSET;
+use crate::display::palette::Palette;
+use crate::input::keymap::{virtual_key_code, KeyMap};
+
+/// `Interpreter::dirty_rows` with every row set.
+pub const ALL_ROWS: u32 = u32::MAX;
 
 pub struct Interpreter {
-    pub memory: [u8; 4096],
+    pub memory: Box<dyn Memory>,
+    pub code_map: CodeMap,
     pub program_counter: u16,
     pub index: u16,
     pub registers: [u8; 16],
@@ -16,14 +28,49 @@ pub struct Interpreter {
     pub delay_timer: u8,
     pub keypad: [bool; 16],
     pub video_output: [u64; 32],
+    /// XO-CHIP's second display plane.
+    pub second_plane: [u64; 32],
+    /// Planes drawn to and cleared, bit 0 for `video_output` and bit 1 for `second_plane`.
+    pub selected_planes: u8,
+    /// Rows of either plane changed since the display was last drawn, bit y
+    /// for row y. Starts with every row set so the first frame is drawn.
+    pub dirty_rows: u32,
+    pub quirks: Quirks,
+    pub rom: Vec<u8>,
+    pub fault: Option<Fault>,
+    pub rng: ChaCha8Rng,
+    pub state: CpuState,
+    pub timing: Timing,
+    /// Time left in the current frame, in the units of `timing`. Negative when
+    /// the last instruction ran over, which is paid back next frame.
+    pub frame_budget: i64,
+    /// Instructions run so far, for measuring speed.
+    pub instructions: u64,
 }
 
 impl Interpreter {
-    pub fn new(rom: &str) -> Interpreter {
-        let mut interpreter = Interpreter {
+    /// Run a ROM already read into memory, with `memory_size` bytes of RAM.
+    /// Fails if the ROM doesn't fit.
+    pub fn from_bytes(bytes: Vec<u8>, memory_size: usize) -> Result<Interpreter, String> {
+        let mut interpreter = Interpreter::blank();
+        if memory_size != MEMORY_SIZE {
+            interpreter.memory = Box::new(Ram::new(memory_size));
+            interpreter.code_map = CodeMap::new(memory_size);
+        }
+        interpreter.load_bytes(bytes)?;
+        Ok(interpreter)
+    }
+
+    /// An interpreter with the font in memory and no program.
+    pub(crate) fn blank() -> Interpreter {
+        Interpreter {
             video_output: [0; 32],
+            second_plane: [0; 32],
+            selected_planes: 1,
+            dirty_rows: ALL_ROWS,
             keypad: [false; 16],
-            memory: [0; 4096],
+            memory: Box::new(Ram::default()),
+            code_map: CodeMap::new(MEMORY_SIZE),
             registers: [0; 16],
             stack: [0; 16],
             program_counter: 0x200,
@@ -31,36 +78,187 @@ impl Interpreter {
             index: 0,
             sound_timer: 0,
             delay_timer: 0,
-        };
-        for i in 0..FONTSET.len() {
-            interpreter.memory[0x50 + i] = FONTSET[i];
+            quirks: Quirks::default(),
+            rom: Vec::new(),
+            fault: None,
+            rng: ChaCha8Rng::from_entropy(),
+            state: CpuState::Running,
+            timing: Timing::default(),
+            frame_budget: 0,
+            instructions: 0,
         }
-        interpreter.load(rom);
-        return interpreter;
     }
 
-    pub fn load(&mut self, rom: &str) {
-        match read(rom) {
-            Ok(bytes) => {
-                for i in 0..bytes.len() {
-                    self.memory[0x200 + i] = bytes[i];
-                }
-                self.program_counter = 0x200;
+    /// Copy a ROM into the program area and jump to it, unless it's too big
+    /// for the memory.
+    pub fn load_bytes(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        check_fits(&bytes, self.memory.len())?;
+        self.memory.poke_range(PROGRAM_START, &bytes);
+        self.program_counter = PROGRAM_START as u16;
+        self.rom = bytes;
+        Ok(())
+    }
+
+    /// Swap in different memory, such as a larger variant's, and load the ROM
+    /// into it.
+    pub fn replace_memory(&mut self, memory: Box<dyn Memory>) {
+        self.memory = memory;
+        self.memory.poke_range(PROGRAM_START, &self.rom);
+        self.code_map = CodeMap::new(self.memory.len());
+    }
+
+    /// Start the ROM again as if just loaded: clear memory, registers, the
+    /// stack, timers and the display, and copy the font and ROM back in.
+    /// Quirks, timing, the memory size and the keypad are kept.
+    pub fn reset(&mut self) {
+        let len = self.memory.len();
+        self.memory.poke_range(0, &vec![0; len]);
+        self.memory.poke_range(FONT_START, &FONTSET);
+        self.memory.poke_range(PROGRAM_START, &self.rom);
+        self.memory.clear_reserved_write();
+        self.code_map = CodeMap::new(len);
+        self.program_counter = PROGRAM_START as u16;
+        self.index = 0;
+        self.registers = [0; 16];
+        self.stack = [0; 16];
+        self.stack_pointer = 0;
+        self.delay_timer = 0;
+        self.sound_timer = 0;
+        self.video_output = [0; 32];
+        self.second_plane = [0; 32];
+        self.selected_planes = 1;
+        self.dirty_rows = ALL_ROWS;
+        self.fault = None;
+        self.state = CpuState::Running;
+        self.frame_budget = 0;
+    }
+
+    /// Reset with a new version of the ROM, such as after it was rebuilt.
+    /// If it's too big for the memory, the old one is left running.
+    pub fn reload(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        check_fits(&bytes, self.memory.len())?;
+        self.rom = bytes;
+        self.reset();
+        Ok(())
+    }
+
+    /// Make Cxnn deterministic by seeding the random number generator. The
+    /// generator is ChaCha8, whose stream for a seed never changes, so seeded
+    /// runs such as the golden tests stay the same across rand releases.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+    }
+
+    /// The opcode at the program counter, if it's in memory.
+    pub fn next_opcode(&self) -> Option<u16> {
+        let address = self.program_counter as usize;
+        if address + 1 >= self.memory.len() {
+            return None;
+        }
+        Some((self.memory.peek(address) as u16) << 8 | self.memory.peek(address + 1) as u16)
+    }
+
+    pub fn update(&mut self) {
+        match self.state {
+            CpuState::Running => {}
+            CpuState::WaitingForKey { .. } => {
+                self.update_key_wait();
+                return;
             }
-            Err(e) => {
-                if e.kind() == std::io::ErrorKind::PermissionDenied {
+            CpuState::WaitingForVblank => return,
+        }
+        let address = self.program_counter as usize;
+        if address + 1 >= self.memory.len() {
+            self.fault = Some(Fault::ProgramCounterOutOfBounds(self.program_counter));
+            return;
+        }
+        let opcode = (self.memory.read(address, Access::Fetch) as u16) << 8
+            | self.memory.read(address + 1, Access::Fetch) as u16;
+        self.code_map.execute(address);
+        self.code_map.execute(address + 1);
+        self.program_counter = self.program_counter.wrapping_add(2);
+        self.instructions += 1;
+        self.decode_and_execute(opcode);
+    }
+
+    /// Whether Fx0A is waiting for input, so frontends can tell the user.
+    pub fn waiting_for_key(&self) -> bool {
+        matches!(self.state, CpuState::WaitingForKey { .. })
+    }
+
+    /// Check the keypad for the key Fx0A is waiting for.
+    fn update_key_wait(&mut self) {
+        let (vx, held, pressed) = match self.state {
+            CpuState::WaitingForKey { vx, held, pressed } => (vx, held, pressed),
+            _ => return,
+        };
+        let key = match pressed {
+            Some(key) => key,
+            None => match (0..16).find(|key| self.keypad[*key] && !held[*key]) {
+                Some(key) => key as u8,
+                None => {
+                    // Keys released since the last check can be pressed again
+                    self.state = CpuState::WaitingForKey {
+                        vx,
+                        held: self.keypad,
+                        pressed,
+                    };
                     return;
                 }
-                panic!("{}", e);
-            }
+            },
+        };
+        if self.quirks.key_release && self.keypad[key as usize] {
+            self.state = CpuState::WaitingForKey {
+                vx,
+                held,
+                pressed: Some(key),
+            };
+            return;
         }
+        self.registers[vx] = key;
+        self.state = CpuState::Running;
     }
 
-    pub fn update(&mut self) {
-        let opcode = (self.memory[self.program_counter as usize] as u16) << 8
-            | self.memory[self.program_counter as usize + 1] as u16;
-        self.program_counter += 2;
-        self.decode_and_execute(opcode);
+    /// Run one 60 Hz frame of instructions, as many as `timing` allows, followed
+    /// by a vertical blank.
+    pub fn update_frame(&mut self) {
+        self.start_frame();
+        while self.update_within_frame() {}
+        self.vblank();
+    }
+
+    /// Give the CPU a new frame's worth of time. Time left over from the last
+    /// frame is lost, like it is when a real CPU idles.
+    pub fn start_frame(&mut self) {
+        self.frame_budget = self.frame_budget.min(0) + self.timing.per_frame() as i64;
+    }
+
+    /// Run the next instruction if there's time left in this frame, returning
+    /// whether it ran.
+    pub fn update_within_frame(&mut self) -> bool {
+        if self.frame_budget <= 0 {
+            return false;
+        }
+        if self.state == CpuState::WaitingForVblank {
+            self.frame_budget = 0;
+            return false;
+        }
+        self.frame_budget -= self.timing.cost(self) as i64;
+        self.update();
+        true
+    }
+
+    /// The 60 Hz vertical blank interrupt: count the timers down and release a
+    /// Dxyn waiting for it.
+    pub fn vblank(&mut self) {
+        self.update_timers();
+        if self.state == CpuState::WaitingForVblank {
+            self.state = CpuState::Running;
+        }
+    }
+
+    /// Count the delay and sound timers down. Call this at 60 Hz.
+    pub fn update_timers(&mut self) {
         if self.delay_timer > 0 {
             self.delay_timer -= 1
         }
@@ -69,44 +267,89 @@ impl Interpreter {
         }
     }
 
-    pub fn draw(&mut self, frame: &mut [u8]) {
-        for (i, pixel) in frame.chunks_exact_mut(4).enumerate() {
-            let x = i % 64;
-            let y = (i as f32 / 64.0).floor() as usize;
-            let output = self.video_output[y] & (1 << x);
+    /// Write a byte for the instruction being run, watching for self-modifying code.
+    pub(crate) fn store(&mut self, address: usize, value: u8) {
+        let old = self.memory.peek(address);
+        self.memory.write(address, value);
+        let new = self.memory.peek(address);
+        let program_counter = self.program_counter.wrapping_sub(2);
+        self.code_map.write(address, program_counter, old, new);
+    }
 
-            let rgba = if output != 0 {
-                [0xff, 0xff, 0xff, 0xff]
-            } else {
-                [0x15, 0x15, 0x15, 0xff]
-            };
-            pixel.copy_from_slice(&rgba);
+    /// Check that `len` bytes starting at `address` are in memory, recording a fault if not.
+    pub fn check_memory(&mut self, address: usize, len: usize) -> bool {
+        if address + len > self.memory.len() {
+            self.fault = Some(Fault::MemoryOutOfBounds(address + len - 1));
+            return false;
         }
+        true
     }
 
-    pub fn update_inputs(&mut self, input: &WinitInputHelper) {
-        self.keypad[0] =
-            input.key_held(VirtualKeyCode::Key1) || input.key_pressed(VirtualKeyCode::Key1);
-        self.keypad[1] =
-            input.key_held(VirtualKeyCode::Key2) || input.key_pressed(VirtualKeyCode::Key2);
-        self.keypad[2] =
-            input.key_held(VirtualKeyCode::Key3) || input.key_pressed(VirtualKeyCode::Key3);
-        self.keypad[3] =
-            input.key_held(VirtualKeyCode::Key4) || input.key_pressed(VirtualKeyCode::Key4);
+    /// Mark the rows that differ from `before`, both planes, as needing a redraw.
+    pub(crate) fn mark_dirty(&mut self, before: &[[u64; 32]; 2]) {
+        let planes = self.video_output.iter().zip(self.second_plane.iter());
+        for (y, (first, second)) in planes.enumerate() {
+            if *first != before[0][y] || *second != before[1][y] {
+                self.dirty_rows |= 1 << y;
+            }
+        }
+    }
+
+    /// Both display planes, `video_output` first.
+    pub fn planes(&self) -> [&[u64; 32]; 2] {
+        [&self.video_output, &self.second_plane]
+    }
+
+    /// The rows changed since the last call, clearing them. Frontends that
+    /// draw the planes themselves can skip frames where this is 0.
+    pub fn take_dirty_rows(&mut self) -> u32 {
+        std::mem::take(&mut self.dirty_rows)
+    }
 
-        self.keypad[4] = input.key_held(VirtualKeyCode::Q) || input.key_pressed(VirtualKeyCode::Q);
-        self.keypad[5] = input.key_held(VirtualKeyCode::W) || input.key_pressed(VirtualKeyCode::W);
-        self.keypad[6] = input.key_held(VirtualKeyCode::E) || input.key_pressed(VirtualKeyCode::E);
-        self.keypad[7] = input.key_held(VirtualKeyCode::R) || input.key_pressed(VirtualKeyCode::R);
+    /// Render the changed rows of both planes into an RGBA frame in
+    /// `palette`'s colours, leaving the rest of the frame as it was. Returns
+    /// whether anything was drawn. Set `dirty_rows` to `ALL_ROWS` to draw
+    /// everything, such as after changing palette.
+    pub fn draw(&mut self, frame: &mut [u8], palette: &Palette) -> bool {
+        let dirty_rows = self.take_dirty_rows();
+        for (y, row) in frame.chunks_exact_mut(64 * 4).enumerate().take(32) {
+            if dirty_rows & (1 << y) == 0 {
+                continue;
+            }
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let lit = |plane: &[u64; 32]| (plane[y] >> x & 1) as usize;
+                let planes = lit(&self.video_output) | lit(&self.second_plane) << 1;
 
-        self.keypad[8] = input.key_held(VirtualKeyCode::A) || input.key_pressed(VirtualKeyCode::A);
-        self.keypad[9] = input.key_held(VirtualKeyCode::S) || input.key_pressed(VirtualKeyCode::S);
-        self.keypad[10] = input.key_held(VirtualKeyCode::D) || input.key_pressed(VirtualKeyCode::D);
-        self.keypad[11] = input.key_held(VirtualKeyCode::F) || input.key_pressed(VirtualKeyCode::F);
+                let color = palette.color(planes);
+                pixel[..3].copy_from_slice(&color);
+                pixel[3] = 0xff;
+            }
+        }
+        dirty_rows != 0
+    }
+
+    /// Hold down the keypad keys whose keyboard keys are down.
+    pub fn update_inputs(&mut self, input: &WinitInputHelper, key_map: &KeyMap) {
+        self.keypad = [false; 16];
+        for (name, key) in key_map.bindings() {
+            if let Some(code) = virtual_key_code(name) {
+                if input.key_held(code) || input.key_pressed(code) {
+                    self.keypad[key as usize] = true;
+                }
+            }
+        }
+    }
+}
 
-        self.keypad[12] = input.key_held(VirtualKeyCode::Z) || input.key_pressed(VirtualKeyCode::Z);
-        self.keypad[13] = input.key_held(VirtualKeyCode::X) || input.key_pressed(VirtualKeyCode::X);
-        self.keypad[14] = input.key_held(VirtualKeyCode::C) || input.key_pressed(VirtualKeyCode::C);
-        self.keypad[15] = input.key_held(VirtualKeyCode::V) || input.key_pressed(VirtualKeyCode::V);
+/// Programs are loaded at 0x200, so a ROM can use the rest of the memory.
+fn check_fits(rom: &[u8], memory_size: usize) -> Result<(), String> {
+    let space = memory_size.saturating_sub(PROGRAM_START);
+    if rom.len() > space {
+        return Err(format!(
+            "the ROM is {} bytes, but only {} fit in memory",
+            rom.len(),
+            space
+        ));
     }
+    Ok(())
 }
@@ -0,0 +1,193 @@
+use std::ops::Range;
+
+use crate::constants::FONTSET;
+
+/// Memory on the original COSMAC VIP and most interpreters since.
+pub const MEMORY_SIZE: usize = 4096;
+/// XO-CHIP's 64K of memory.
+pub const XOCHIP_MEMORY_SIZE: usize = 65536;
+/// Where the hex font is loaded.
+pub const FONT_START: usize = 0x50;
+/// Where programs are loaded. Everything below belonged to the interpreter.
+pub const PROGRAM_START: usize = 0x200;
+
+/// Memory size for a chip-8-database platform id.
+pub fn size_for_platform(platform: &str) -> usize {
+    match platform {
+        "xochip" => XOCHIP_MEMORY_SIZE,
+        _ => MEMORY_SIZE,
+    }
+}
+
+/// Parts of the address space.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Region {
+    /// Below the program, where the original interpreter lived.
+    Interpreter,
+    /// The hex font, inside the interpreter area.
+    Font,
+    /// From 0x200 on.
+    Program,
+}
+
+impl Region {
+    pub fn of(address: usize) -> Region {
+        if Region::font().contains(&address) {
+            Region::Font
+        } else if address < PROGRAM_START {
+            Region::Interpreter
+        } else {
+            Region::Program
+        }
+    }
+
+    fn font() -> Range<usize> {
+        FONT_START..FONT_START + FONTSET.len()
+    }
+}
+
+/// Ways the program can touch memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    /// Reading an instruction.
+    Fetch,
+    /// Reading data, for sprites and Fx65.
+    Read,
+    /// Writing data, from Fx33 and Fx55.
+    Write,
+}
+
+/// Called with every access the program makes, its address and the byte.
+pub type Observer = Box<dyn FnMut(Access, usize, u8)>;
+
+/// Memory behind the interpreter.
+///
+/// `read` and `write` are what the program does, so implementations can watch
+/// or refuse them. `peek` and `poke` are for loading ROMs, frontends and tests,
+/// and are never observed.
+pub trait Memory {
+    /// Size of the address space in bytes.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn peek(&self, address: usize) -> u8;
+
+    fn poke(&mut self, address: usize, value: u8);
+
+    fn read(&mut self, address: usize, _access: Access) -> u8 {
+        self.peek(address)
+    }
+
+    fn write(&mut self, address: usize, value: u8) {
+        self.poke(address, value)
+    }
+
+    /// The first address below the program area the program wrote to, if
+    /// this memory keeps track. Some games keep data there, but it usually
+    /// means a bad index.
+    fn reserved_write(&self) -> Option<usize> {
+        None
+    }
+
+    /// Forget the write `reserved_write` reports, such as when the program
+    /// starts again.
+    fn clear_reserved_write(&mut self) {}
+
+    /// Copy `len` bytes starting at `address` out without observing them.
+    fn peek_range(&self, address: usize, len: usize) -> Vec<u8> {
+        (address..address + len)
+            .map(|address| self.peek(address))
+            .collect()
+    }
+
+    /// Copy `bytes` in at `address` without observing them.
+    fn poke_range(&mut self, address: usize, bytes: &[u8]) {
+        for (offset, byte) in bytes.iter().enumerate() {
+            self.poke(address + offset, *byte);
+        }
+    }
+}
+
+/// Plain RAM, divided into the regions of `Region`.
+pub struct Ram {
+    bytes: Vec<u8>,
+    /// Drop writes to the program area, as if the program were in ROM.
+    pub write_protect: bool,
+    pub observer: Option<Observer>,
+    reserved_write: Option<usize>,
+}
+
+impl Ram {
+    /// `size` bytes of zeroes with the font loaded.
+    pub fn new(size: usize) -> Ram {
+        let mut ram = Ram {
+            bytes: vec![0; size],
+            write_protect: false,
+            observer: None,
+            reserved_write: None,
+        };
+        ram.poke_range(FONT_START, &FONTSET);
+        ram
+    }
+}
+
+impl Default for Ram {
+    fn default() -> Ram {
+        Ram::new(MEMORY_SIZE)
+    }
+}
+
+impl Memory for Ram {
+    fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    fn peek(&self, address: usize) -> u8 {
+        self.bytes[address]
+    }
+
+    fn poke(&mut self, address: usize, value: u8) {
+        self.bytes[address] = value;
+    }
+
+    fn read(&mut self, address: usize, access: Access) -> u8 {
+        let value = self.bytes[address];
+        if let Some(observer) = &mut self.observer {
+            observer(access, address, value);
+        }
+        value
+    }
+
+    fn write(&mut self, address: usize, value: u8) {
+        if let Some(observer) = &mut self.observer {
+            observer(Access::Write, address, value);
+        }
+        match Region::of(address) {
+            Region::Program if self.write_protect => return,
+            Region::Program => {}
+            _ => {
+                self.reserved_write.get_or_insert(address);
+            }
+        }
+        self.bytes[address] = value;
+    }
+
+    fn reserved_write(&self) -> Option<usize> {
+        self.reserved_write
+    }
+
+    fn clear_reserved_write(&mut self) {
+        self.reserved_write = None;
+    }
+
+    fn peek_range(&self, address: usize, len: usize) -> Vec<u8> {
+        self.bytes[address..address + len].to_vec()
+    }
+
+    fn poke_range(&mut self, address: usize, bytes: &[u8]) {
+        self.bytes[address..address + bytes.len()].copy_from_slice(bytes);
+    }
+}
@@ -1,3 +1,14 @@
+pub mod builder;
+pub mod clock;
+pub mod code_map;
 pub mod decode;
 pub mod execute;
+pub mod fault;
+#[allow(clippy::module_inception)]
 pub mod interpreter;
+pub mod memory;
+pub mod quirks;
+pub mod snapshot;
+pub mod sprite;
+pub mod state;
+pub mod timing;
@@ -0,0 +1,166 @@
+/// The names `Quirks::profile` knows.
+pub const PROFILES: [&str; 5] = ["default", "chip8", "schip", "modern", "xochip"];
+
+/// Behaviours that differ between CHIP-8 implementations.
+///
+/// Field names follow the quirk names used by the community
+/// [chip-8-database](https://github.com/chip-8/chip-8-database).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quirks {
+    /// 8xy6/8xyE shift Vx in place instead of shifting Vy into Vx.
+    pub shift: bool,
+    /// Fx55/Fx65 increment I by x instead of x + 1.
+    pub memory_increment_by_x: bool,
+    /// Fx55/Fx65 leave I unchanged.
+    pub memory_leave_i_unchanged: bool,
+    /// Bnnn jumps to xnn + Vx instead of nnn + V0.
+    pub jump: bool,
+    /// 8xy1/8xy2/8xy3 reset Vf to zero.
+    pub logic: bool,
+    /// Dxyn waits for the next 60 Hz vertical blank after drawing, so at most one
+    /// sprite is drawn per frame.
+    pub vblank: bool,
+    /// Dxyn wraps sprites around the screen edges instead of clipping them.
+    pub wrap: bool,
+    /// Fx0A waits for the key to be released as well as pressed, like the COSMAC VIP.
+    /// This isn't a database quirk, so ROM entries never change it.
+    pub key_release: bool,
+    /// Dxy0 draws a 16x16 sprite instead of nothing. This isn't a database
+    /// quirk, it comes with the SUPER-CHIP and XO-CHIP platforms.
+    pub large_sprites: bool,
+}
+
+impl Quirks {
+    /// The original COSMAC VIP interpreter.
+    pub fn chip8() -> Quirks {
+        Quirks {
+            shift: false,
+            memory_increment_by_x: false,
+            memory_leave_i_unchanged: false,
+            jump: false,
+            logic: true,
+            vblank: true,
+            wrap: false,
+            key_release: true,
+            large_sprites: false,
+        }
+    }
+
+    /// SUPER-CHIP 1.1 on the HP 48.
+    pub fn schip() -> Quirks {
+        Quirks {
+            shift: true,
+            memory_increment_by_x: false,
+            memory_leave_i_unchanged: true,
+            jump: true,
+            logic: false,
+            vblank: false,
+            wrap: false,
+            key_release: false,
+            large_sprites: true,
+        }
+    }
+
+    /// The chip-8-database `modernChip8` platform, as most modern interpreters
+    /// behave.
+    pub fn modern() -> Quirks {
+        Quirks {
+            shift: false,
+            memory_increment_by_x: false,
+            memory_leave_i_unchanged: false,
+            jump: false,
+            logic: false,
+            vblank: false,
+            wrap: false,
+            key_release: false,
+            large_sprites: false,
+        }
+    }
+
+    /// XO-CHIP as implemented by Octo. The database quirks are the chip-8-database
+    /// `xochip` platform's, which differ from `modern` only in `wrap`; Octo also
+    /// draws 16x16 sprites and resolves Fx0A on key release.
+    pub fn xochip() -> Quirks {
+        Quirks {
+            shift: false,
+            memory_increment_by_x: false,
+            memory_leave_i_unchanged: false,
+            jump: false,
+            logic: false,
+            vblank: false,
+            wrap: true,
+            key_release: true,
+            large_sprites: true,
+        }
+    }
+
+    /// Look up a profile by one of the names in `PROFILES`.
+    pub fn profile(name: &str) -> Option<Quirks> {
+        match name {
+            "default" => Some(Quirks::default()),
+            "chip8" => Some(Quirks::chip8()),
+            "schip" => Some(Quirks::schip()),
+            "modern" => Some(Quirks::modern()),
+            "xochip" => Some(Quirks::xochip()),
+            _ => None,
+        }
+    }
+
+    /// The name of the profile these quirks match, if any.
+    pub fn profile_name(&self) -> Option<&'static str> {
+        PROFILES
+            .iter()
+            .find(|name| Quirks::profile(name) == Some(*self))
+            .copied()
+    }
+
+    /// Look up a profile by its chip-8-database platform id.
+    pub fn for_platform(platform: &str) -> Option<Quirks> {
+        match platform {
+            "originalChip8" | "hybridVIP" => Some(Quirks::chip8()),
+            "modernChip8" => Some(Quirks::modern()),
+            "superchip1" | "superchip" => Some(Quirks::schip()),
+            // CHIP-48 has no profile of its own: it is SUPER-CHIP's predecessor
+            // without 16x16 sprites, and Fx55/Fx65 leave I advanced by x
+            "chip48" => Some(Quirks {
+                memory_increment_by_x: true,
+                memory_leave_i_unchanged: false,
+                large_sprites: false,
+                ..Quirks::schip()
+            }),
+            "xochip" => Some(Quirks::xochip()),
+            _ => None,
+        }
+    }
+
+    /// Set a quirk by its chip-8-database name. Unknown names are ignored.
+    pub fn set(&mut self, name: &str, value: bool) {
+        match name {
+            "shift" => self.shift = value,
+            "memoryIncrementByX" => self.memory_increment_by_x = value,
+            "memoryLeaveIUnchanged" => self.memory_leave_i_unchanged = value,
+            "jump" => self.jump = value,
+            "logic" => self.logic = value,
+            "vblank" => self.vblank = value,
+            "wrap" => self.wrap = value,
+            _ => {}
+        }
+    }
+}
+
+impl Default for Quirks {
+    /// Shift in place, leave I unchanged and wrap sprites, which suits most of the bundled games.
+    fn default() -> Quirks {
+        Quirks {
+            shift: true,
+            memory_increment_by_x: false,
+            memory_leave_i_unchanged: true,
+            jump: false,
+            logic: false,
+            vblank: false,
+            wrap: true,
+            key_release: false,
+            large_sprites: false,
+        }
+    }
+}
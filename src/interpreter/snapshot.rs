@@ -0,0 +1,121 @@
+use std::fs::{create_dir_all, read_to_string, write};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::code_map::CodeMap;
+use super::interpreter::{Interpreter, ALL_ROWS};
+use super::state::CpuState;
+use crate::database::sha1_hex;
+
+/// Where frontends keep save states, in the working directory.
+pub const SAVES_DIR: &str = "saves";
+
+/// Save state slots are numbered from 1 to this.
+pub const SAVE_SLOTS: u8 = 4;
+
+/// Everything the running program can see, to go back to later.
+///
+/// Settings such as quirks and timing aren't part of it, and neither is the
+/// random number generator, so Cxnn goes its own way after a restore.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub memory: Vec<u8>,
+    pub program_counter: u16,
+    pub index: u16,
+    pub registers: [u8; 16],
+    pub stack: [u16; 16],
+    pub stack_pointer: usize,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub video_output: [u64; 32],
+    pub second_plane: [u64; 32],
+    pub selected_planes: u8,
+    pub state: CpuState,
+    pub frame_budget: i64,
+}
+
+impl Snapshot {
+    pub fn load(path: &Path) -> io::Result<Snapshot> {
+        serde_json::from_str(&read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Write the snapshot, creating its directory if needed.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            create_dir_all(dir)?;
+        }
+        write(path, serde_json::to_string(self)? + "\n")
+    }
+}
+
+/// The file for `rom`'s save state in `slot`, named after the ROM's hash so
+/// renaming the ROM keeps its saves.
+pub fn save_path(dir: &Path, rom: &[u8], slot: u8) -> PathBuf {
+    dir.join(format!("{}-{}.json", sha1_hex(rom), slot))
+}
+
+impl Interpreter {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            memory: self.memory.peek_range(0, self.memory.len()),
+            program_counter: self.program_counter,
+            index: self.index,
+            registers: self.registers,
+            stack: self.stack,
+            stack_pointer: self.stack_pointer,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            video_output: self.video_output,
+            second_plane: self.second_plane,
+            selected_planes: self.selected_planes,
+            state: self.state,
+            frame_budget: self.frame_budget,
+        }
+    }
+
+    /// Go back to a snapshot. It must have been taken with the same size of
+    /// memory, and is checked for values the interpreter can't run with,
+    /// since it may have been edited on disk.
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), String> {
+        if snapshot.memory.len() != self.memory.len() {
+            return Err(format!(
+                "the save state has {} bytes of memory, not {}",
+                snapshot.memory.len(),
+                self.memory.len()
+            ));
+        }
+        if snapshot.stack_pointer > snapshot.stack.len() {
+            return Err(format!(
+                "the save state's stack pointer {} is past the stack",
+                snapshot.stack_pointer
+            ));
+        }
+        if let CpuState::WaitingForKey { vx, pressed, .. } = snapshot.state {
+            if vx >= snapshot.registers.len() || pressed.is_some_and(|key| key > 0xf) {
+                return Err(String::from(
+                    "the save state is waiting for a key it can't have",
+                ));
+            }
+        }
+        self.memory.poke_range(0, &snapshot.memory);
+        self.code_map = CodeMap::new(snapshot.memory.len());
+        self.program_counter = snapshot.program_counter;
+        self.index = snapshot.index;
+        self.registers = snapshot.registers;
+        self.stack = snapshot.stack;
+        self.stack_pointer = snapshot.stack_pointer;
+        self.delay_timer = snapshot.delay_timer;
+        self.sound_timer = snapshot.sound_timer;
+        self.video_output = snapshot.video_output;
+        self.second_plane = snapshot.second_plane;
+        self.selected_planes = snapshot.selected_planes;
+        self.state = snapshot.state;
+        self.frame_budget = snapshot.frame_budget;
+        self.fault = None;
+        self.dirty_rows = ALL_ROWS;
+        Ok(())
+    }
+}
@@ -0,0 +1,37 @@
+use crate::constants::{WINDOW_HEIGHT, WINDOW_WIDTH};
+
+/// XOR a sprite onto one display plane and report whether any lit pixel was turned off.
+///
+/// Each row of `rows` holds `width` pixels in its low bits, leftmost pixel in the
+/// highest of them. `x` and `y` must already be on screen. Pixels past the right or
+/// bottom edge wrap around when `wrap` is set and are clipped otherwise.
+pub fn blit(plane: &mut [u64; 32], x: u32, y: u32, rows: &[u16], width: u32, wrap: bool) -> bool {
+    let mut collision = false;
+    for (row, bits) in rows.iter().enumerate() {
+        let mut screen_y = y + row as u32;
+        if screen_y >= WINDOW_HEIGHT {
+            if !wrap {
+                break;
+            }
+            screen_y %= WINDOW_HEIGHT;
+        }
+        let line = &mut plane[screen_y as usize];
+
+        for column in 0..width {
+            if bits & (1 << (width - 1 - column)) == 0 {
+                continue;
+            }
+            let mut screen_x = x + column;
+            if screen_x >= WINDOW_WIDTH {
+                if !wrap {
+                    break;
+                }
+                screen_x %= WINDOW_WIDTH;
+            }
+            let pixel = 1 << screen_x;
+            collision |= *line & pixel != 0;
+            *line ^= pixel;
+        }
+    }
+    collision
+}
@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+/// What the CPU is doing between instructions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CpuState {
+    /// Fetching and executing instructions.
+    Running,
+    /// Fx0A is waiting for a key to store in Vx.
+    WaitingForKey {
+        vx: usize,
+        /// Keys that were down when last checked. Only keys pressed since count.
+        held: [bool; 16],
+        /// The key that was pressed, when waiting for it to be released.
+        pressed: Option<u8>,
+    },
+    /// Dxyn is waiting for the vertical blank with the vblank quirk.
+    WaitingForVblank,
+}
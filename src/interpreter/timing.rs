@@ -0,0 +1,133 @@
+//! How much the CPU runs each frame, either a fixed number of instructions
+//! or what the original COSMAC VIP interpreter would have managed.
+//!
+//! `Timing::CosmacVip` is a model, not an emulation, and only some of it is
+//! exact:
+//!
+//! - The frame is exact: 1.7609 MHz in 8-clock machine cycles, less what the
+//!   CDP1861's display DMA and interrupt routine take each frame.
+//! - The costs that depend on operands are worked out from the interpreter's
+//!   routines: skips taken, Dxyn's per-row cost for the sprite's height and
+//!   how far it is from a byte boundary, Fx33's repeated subtraction and
+//!   Fx55/Fx65's per-register loop. These grow the way the real ones do.
+//! - The constant parts are approximate. Fetching and decoding is charged a
+//!   flat `VIP_FETCH_CYCLES`, though the real dispatch takes a few cycles more
+//!   or less depending on the opcode, and each handler's fixed cost is rounded.
+//! - Display DMA is charged as one block per frame rather than stealing
+//!   cycles partway through instructions, so where an instruction falls in
+//!   the frame can be off by a few instructions.
+//!
+//! Exact numbers would need the interpreter itself, which isn't included. For
+//! cycle-exact timing, `vip::Vip` runs the real interpreter when given
+//! images of it and the monitor ROM.
+
+use super::interpreter::Interpreter;
+use super::state::CpuState;
+use crate::constants::DEFAULT_TICK_RATE;
+
+/// The COSMAC VIP's 1.7609 MHz clock in 8-clock machine cycles per 60 Hz frame.
+pub const VIP_CYCLES_PER_FRAME: u32 = 1_760_900 / 8 / 60;
+/// Machine cycles per frame the CDP1861 takes for display DMA and its interrupt routine.
+pub const VIP_DISPLAY_CYCLES: u32 = 1024 + 48;
+/// Machine cycles the interpreter spends fetching and decoding each
+/// instruction. The real cost varies by a few cycles with the opcode.
+pub const VIP_FETCH_CYCLES: u32 = 40;
+
+/// How much work the CPU does in each 60 Hz frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timing {
+    /// Run a fixed number of instructions per frame, whatever they are.
+    Instructions(u32),
+    /// Charge every instruction about what it costs the original COSMAC VIP
+    /// interpreter, in machine cycles. See the module documentation for what
+    /// is exact.
+    CosmacVip,
+}
+
+impl Default for Timing {
+    fn default() -> Timing {
+        Timing::Instructions(DEFAULT_TICK_RATE)
+    }
+}
+
+impl Timing {
+    /// The time available in one frame, in the units `cost` returns.
+    pub fn per_frame(self) -> u32 {
+        match self {
+            Timing::Instructions(tick_rate) => tick_rate,
+            Timing::CosmacVip => VIP_CYCLES_PER_FRAME - VIP_DISPLAY_CYCLES,
+        }
+    }
+
+    /// What running the interpreter's next instruction will cost.
+    pub fn cost(self, interpreter: &Interpreter) -> u32 {
+        match self {
+            Timing::Instructions(_) => 1,
+            Timing::CosmacVip => vip_cycles(interpreter),
+        }
+    }
+}
+
+/// Machine cycles the COSMAC VIP interpreter takes for the next instruction.
+///
+/// How the costs change with operands follows the VIP interpreter's routines,
+/// including the extra cycles when a skip is taken and the per-row cost of
+/// shifting sprites that aren't byte aligned. The fixed part of each cost is
+/// approximate, as the module documentation explains.
+pub fn vip_cycles(interpreter: &Interpreter) -> u32 {
+    if let CpuState::WaitingForKey { .. } = interpreter.state {
+        // One pass of the keypad scanning loop
+        return 68;
+    }
+    let opcode = match interpreter.next_opcode() {
+        Some(opcode) => opcode,
+        None => return VIP_FETCH_CYCLES,
+    };
+    let x = ((opcode & 0x0f00) >> 8) as usize;
+    let y = ((opcode & 0x00f0) >> 4) as usize;
+    let n = (opcode & 0x000f) as u32;
+    let nn = (opcode & 0x00ff) as u8;
+    let vx = interpreter.registers[x];
+    let vy = interpreter.registers[y];
+    let skip = |taken: bool| if taken { 14 } else { 10 };
+
+    let execute = match opcode >> 12 {
+        0x0 => match opcode {
+            0x00e0 => 3078,
+            0x00ee => 10,
+            _ => 0,
+        },
+        0x1 => 12,
+        0x2 => 26,
+        0x3 => skip(vx == nn),
+        0x4 => skip(vx != nn),
+        0x5 => 4 + skip(vx == vy),
+        0x6 => 6,
+        0x7 => 10,
+        0x8 => 44,
+        0x9 => 4 + skip(vx != vy),
+        0xa => 12,
+        0xb => 22,
+        0xc => 36,
+        0xd => {
+            // Each row is shifted into place one bit at a time, and spills
+            // into a second byte unless x is byte aligned
+            let shift = vx as u32 % 8;
+            let row = 34 + 8 * shift + if shift == 0 { 0 } else { 12 };
+            26 + n * row
+        }
+        0xe => 4 + skip(interpreter.keypad[vx as usize & 0xf] == (nn == 0x9e)),
+        0xf => match nn {
+            0x07 | 0x15 | 0x18 => 10,
+            0x0a => 20,
+            0x1e => 16,
+            0x29 => 16,
+            // Repeated subtraction of 100s and 10s
+            0x33 => 84 + 16 * (vx / 100 + vx % 100 / 10 + vx % 10) as u32,
+            0x55 | 0x65 => 14 + 14 * (x as u32 + 1),
+            _ => 0,
+        },
+        _ => 0,
+    };
+    VIP_FETCH_CYCLES + execute
+}
@@ -1,3 +1,10 @@
 pub mod constants;
+pub mod database;
 pub mod display;
+pub mod headless;
+pub mod input;
 pub mod interpreter;
+pub mod machine;
+pub mod settings;
+pub mod vip;
+pub mod watch;
@@ -0,0 +1,78 @@
+use crate::interpreter::interpreter::Interpreter;
+use crate::vip::Vip;
+
+/// What a frontend needs to run a CHIP-8, so it can use either our
+/// `Interpreter` or a `Vip` running the original interpreter.
+pub trait Machine {
+    /// Run one 60 Hz frame.
+    fn update_frame(&mut self);
+    /// Keys held on the hex keypad.
+    fn keypad(&mut self) -> &mut [bool; 16];
+    /// The display, bit x of row y for the pixel at (x, y).
+    fn video_output(&self) -> [u64; 32];
+    /// Both display planes, `video_output` first. Machines without XO-CHIP's
+    /// second plane leave it blank.
+    fn planes(&self) -> [[u64; 32]; 2] {
+        [self.video_output(), [0; 32]]
+    }
+    /// Whether the buzzer is sounding.
+    fn sound_active(&self) -> bool;
+    /// Whether the program is stopped waiting for a key, if the machine can tell.
+    fn waiting_for_key(&self) -> bool {
+        false
+    }
+    /// The program being run.
+    fn rom(&self) -> &[u8];
+}
+
+impl Machine for Interpreter {
+    fn update_frame(&mut self) {
+        Interpreter::update_frame(self)
+    }
+
+    fn keypad(&mut self) -> &mut [bool; 16] {
+        &mut self.keypad
+    }
+
+    fn video_output(&self) -> [u64; 32] {
+        self.video_output
+    }
+
+    fn planes(&self) -> [[u64; 32]; 2] {
+        [self.video_output, self.second_plane]
+    }
+
+    fn sound_active(&self) -> bool {
+        self.sound_timer > 0
+    }
+
+    fn waiting_for_key(&self) -> bool {
+        Interpreter::waiting_for_key(self)
+    }
+
+    fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+}
+
+impl Machine for Vip {
+    fn update_frame(&mut self) {
+        Vip::update_frame(self)
+    }
+
+    fn keypad(&mut self) -> &mut [bool; 16] {
+        Vip::keypad(self)
+    }
+
+    fn video_output(&self) -> [u64; 32] {
+        Vip::video_output(self)
+    }
+
+    fn sound_active(&self) -> bool {
+        Vip::sound_active(self)
+    }
+
+    fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+}
@@ -1,41 +1,271 @@
+use std::collections::HashMap;
+use std::fs::read;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::exit;
 use std::time::Instant;
 
 use hoyer_chip_8::{
-    constants::{WINDOW_HEIGHT, WINDOW_WIDTH},
-    display::window::create_window,
-    interpreter::interpreter::Interpreter,
+    constants::{FRAME_DURATION, WINDOW_HEIGHT, WINDOW_WIDTH},
+    database::{
+        library::{list_roms, ROM_DIR},
+        RomDatabase,
+    },
+    display::{
+        effects::{Effects, Renderer, CPU_SCALE},
+        menu::{Menu, MenuAction, MenuKey},
+        overlay::Overlay,
+        palette::Palette,
+        persistence::{Filter, Persistence, FILTERS},
+        scaling::Scaling,
+        screenshot::{ImageFormat, Screenshot},
+        shader::CrtRenderer,
+        window::create_window,
+    },
+    input::{
+        gamepad::Gamepads,
+        keymap::{KeyConfig, KeyMap, CONFIG_FILE},
+    },
+    interpreter::{
+        clock::{format_speed, Clock},
+        interpreter::{Interpreter, ALL_ROWS},
+        snapshot::{save_path, Snapshot, SAVES_DIR},
+    },
+    settings::{Settings, SETTINGS_FILE},
+    watch::RomWatcher,
 };
-use pixels::{wgpu, Pixels, PixelsBuilder, SurfaceTexture};
+use pixels::{wgpu, PixelsBuilder, SurfaceTexture};
 use winit::{
-    dpi::PhysicalSize,
+    dpi::{LogicalSize, PhysicalSize},
     event::{Event, VirtualKeyCode},
     event_loop::{ControlFlow, EventLoop},
+    window::Fullscreen,
 };
 use winit_input_helper::WinitInputHelper;
 
+const TITLE: &str = "Hoyer's Chip-8 Interpreter";
+
+const USAGE: &str = "Usage: hoyer-chip-8 [ROM] [--watch] [--roms <DIR>] \
+                     [--palette <THEME|#rrggbb,...>] [--filter <off|blend|decay|stable>]";
+
+/// A ROM and everything set up for it.
+struct Game {
+    path: PathBuf,
+    interpreter: Interpreter,
+    /// The window title.
+    title: String,
+    /// The ROM's title from the database, or its file name.
+    name: String,
+    key_map: KeyMap,
+    gamepads: Gamepads,
+    palette: Palette,
+}
+
+impl Game {
+    /// Load a ROM, configured from the database, settings and key
+    /// configuration.
+    fn load(path: &Path, settings: &Settings, config: &KeyConfig) -> io::Result<Game> {
+        let (interpreter, info) = RomDatabase::builtin()
+            .load(read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        // Configure ourselves for known ROMs
+        let mut title = String::from(TITLE);
+        let mut name = path
+            .file_stem()
+            .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+        let mut actions = HashMap::new();
+        let mut rom_palette = None;
+        if let Some(info) = info {
+            title = format!("{} - {}", info.title, title);
+            name = info.title.clone();
+            actions = info.keys.clone();
+            rom_palette = info.palette();
+        }
+
+        Ok(Game {
+            path: path.to_path_buf(),
+            key_map: config.key_map(&interpreter.rom),
+            gamepads: Gamepads::for_actions(&config.gamepad_actions(&interpreter.rom, &actions)),
+            palette: settings.palette(rom_palette),
+            interpreter,
+            title,
+            name,
+        })
+    }
+
+    /// Carry out a menu action that doesn't change the ROM, returning a
+    /// message for the overlay.
+    fn apply(&mut self, action: MenuAction) -> Option<String> {
+        let interpreter = &mut self.interpreter;
+        match action {
+            MenuAction::Timing(timing) => interpreter.timing = timing,
+            MenuAction::Quirks(quirks) => interpreter.quirks = quirks,
+            MenuAction::SaveState(slot) => {
+                let path = save_path(Path::new(SAVES_DIR), &interpreter.rom, slot);
+                return Some(match interpreter.snapshot().save(&path) {
+                    Ok(()) => format!("State saved to slot {}", slot),
+                    Err(e) => format!("Failed to save state: {}", e),
+                });
+            }
+            MenuAction::LoadState(slot) => {
+                let path = save_path(Path::new(SAVES_DIR), &interpreter.rom, slot);
+                let restored = Snapshot::load(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|snapshot| interpreter.restore(&snapshot));
+                return Some(match restored {
+                    Ok(()) => format!("State loaded from slot {}", slot),
+                    Err(e) => format!("Failed to load state: {}", e),
+                });
+            }
+            MenuAction::Reset => {
+                interpreter.reset();
+                return Some(String::from("Reset"));
+            }
+            MenuAction::Resume | MenuAction::Load(_) => {}
+        }
+        None
+    }
+}
+
+/// Watch a ROM for changes, or say why it can't be.
+fn watch_rom(path: &Path) -> Option<RomWatcher> {
+    RomWatcher::new(path)
+        .map_err(|e| eprintln!("Can't watch {}: {}", path.display(), e))
+        .ok()
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("hoyer-chip-8: {}", message);
+    exit(2);
+}
+
 fn main() {
     let event_loop = EventLoop::new();
     let mut input = WinitInputHelper::new();
-    let (window, p_width, p_height, mut _hidpi_factor) =
-        create_window("Hoyer's Chip-8 Interpreter", &event_loop);
+
+    let mut settings = Settings::load_default().unwrap_or_else(|e| {
+        eprintln!("{}: {}", SETTINGS_FILE, e);
+        Settings::default()
+    });
+    let mut rom_path = PathBuf::from(ROM_DIR).join("PONG.c8");
+    let mut rom_dir = String::from(ROM_DIR);
+    let mut watch = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .unwrap_or_else(|| fail(&format!("{} needs a value", arg)))
+        };
+        match arg.as_str() {
+            "--palette" => {
+                let spec = value();
+                if let Err(e) = Palette::parse(&spec) {
+                    fail(&format!("--palette: {}", e));
+                }
+                settings.palette = Some(spec);
+            }
+            "--filter" => match Filter::from_name(&value()) {
+                Some(filter) => settings.filter = Some(filter),
+                None => fail(&format!("--filter: expected one of {}", FILTERS.join(", "))),
+            },
+            "--roms" => rom_dir = value(),
+            "--watch" => watch = true,
+            option if option.starts_with('-') => {
+                fail(&format!("unknown option {}\n\n{}", option, USAGE))
+            }
+            _ => rom_path = PathBuf::from(arg),
+        }
+    }
+    let mut persistence = Persistence::new(settings.filter.unwrap_or_default());
+
+    let config = KeyConfig::load_default().unwrap_or_else(|e| {
+        eprintln!("{}: {}", CONFIG_FILE, e);
+        KeyConfig::default()
+    });
+    let mut game = Game::load(&rom_path, &settings, &config).unwrap_or_else(|e| {
+        eprintln!("{}: {}", rom_path.display(), e);
+        exit(1);
+    });
+    let roms = list_roms(Path::new(&rom_dir), &RomDatabase::builtin()).unwrap_or_else(|e| {
+        eprintln!("{}: {}", rom_dir, e);
+        Vec::new()
+    });
+    let mut menu = Menu::new(roms, &rom_dir);
+    let mut watcher = if watch { watch_rom(&game.path) } else { None };
+
+    let (window, p_width, p_height) = create_window(&game.title, &event_loop);
     let surface_texture = SurfaceTexture::new(p_width, p_height, &window);
-    let mut pixels = PixelsBuilder::new(WINDOW_WIDTH, WINDOW_HEIGHT, surface_texture)
-        .present_mode(wgpu::PresentMode::Immediate)
-        .request_adapter_options(wgpu::RequestAdapterOptions {
-            power_preference: wgpu::PowerPreference::HighPerformance,
-            compatible_surface: None,
-        })
-        .wgpu_backend(wgpu::BackendBit::DX12)
-        .build()
-        .unwrap();
-    let mut interpreter = Interpreter::new("./roms/games/PONG.c8");
+    // Effects drawn on the CPU need more pixels than the display has
+    let cpu_scale = match settings.renderer {
+        Renderer::Cpu if !settings.effects.is_off() => CPU_SCALE,
+        _ => 1,
+    };
+    let mut pixels = PixelsBuilder::new(
+        WINDOW_WIDTH * cpu_scale,
+        WINDOW_HEIGHT * cpu_scale,
+        surface_texture,
+    )
+    .present_mode(wgpu::PresentMode::Immediate)
+    .request_adapter_options(wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::HighPerformance,
+        compatible_surface: None,
+    })
+    .wgpu_backend(wgpu::BackendBit::DX12)
+    .build()
+    .unwrap();
+    // Everything is drawn through the effects shader, with no effects if
+    // they are drawn on the CPU, since it can scale by fractions
+    let (renderer, effects) = (settings.renderer, settings.effects);
+    let gpu_effects = move |on: bool| match renderer {
+        Renderer::Gpu if on => effects,
+        _ => Effects::default(),
+    };
+    let mut crt = CrtRenderer::new(
+        &pixels,
+        wgpu::TextureFormat::Bgra8UnormSrgb,
+        gpu_effects(true),
+        settings.scaling,
+        (p_width, p_height),
+    );
+    if settings.fullscreen {
+        window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+    }
+    let mut screen = vec![0; (WINDOW_WIDTH * WINDOW_HEIGHT * 4) as usize];
+    let mut effects_on = true;
+    let mut redraw_rows = ALL_ROWS;
+    let mut overlay = Overlay::new(&game.name, Instant::now());
 
+    let mut clock = Clock::default();
     let mut time = Instant::now();
+    let mut waiting_for_key = false;
     event_loop.run(move |event, _, control_flow| {
         if let Event::RedrawRequested(_) = event {
-            interpreter.draw(pixels.get_frame());
+            // The frame keeps what was drawn before, so only changed rows
+            // are painted again
+            let rows = std::mem::take(&mut redraw_rows);
+            if cpu_scale > 1 {
+                let effects = if effects_on {
+                    settings.effects
+                } else {
+                    Effects::default()
+                };
+                // Curvature and bloom move pixels between rows, so any
+                // change shades the whole frame again
+                if rows != 0 {
+                    persistence.draw(&mut screen, &game.palette, rows);
+                    effects.apply(
+                        &screen,
+                        (WINDOW_WIDTH, WINDOW_HEIGHT),
+                        pixels.get_frame(),
+                        (WINDOW_WIDTH * cpu_scale, WINDOW_HEIGHT * cpu_scale),
+                    );
+                }
+            } else {
+                persistence.draw(pixels.get_frame(), &game.palette, rows);
+            }
             if pixels
-                .render()
+                .render_with(|encoder, target, _| crt.render(encoder, target))
                 .map_err(|e| eprintln!("pixels.render() failed: {}", e))
                 .is_err()
             {
@@ -44,25 +274,225 @@ fn main() {
             }
         }
         if input.update(&event) {
-            if input.key_pressed(VirtualKeyCode::Escape) || input.quit() {
+            if (input.key_pressed(VirtualKeyCode::Escape) && !menu.is_open()) || input.quit() {
                 *control_flow = ControlFlow::Exit;
                 return;
             }
-            // Adjust high DPI factor
-            if let Some(factor) = input.scale_factor_changed() {
-                _hidpi_factor = factor;
+
+            // Open and close the menu, and pass it keys while it's open
+            if input.key_pressed(VirtualKeyCode::F2) {
+                if menu.is_open() {
+                    menu.close();
+                } else {
+                    menu.open(game.interpreter.timing, game.interpreter.quirks);
+                    game.interpreter.keypad = [false; 16];
+                }
             }
-            // Resize the window
-            if let Some(size) = input.window_resized() {
-                if size.height != size.width / 2 {
-                    window.set_inner_size(PhysicalSize::new(size.width, size.width / 2));
-                    pixels.resize_surface(size.width, size.width / 2);
+            let menu_keys = [
+                (VirtualKeyCode::Up, MenuKey::Up),
+                (VirtualKeyCode::Down, MenuKey::Down),
+                (VirtualKeyCode::Left, MenuKey::Left),
+                (VirtualKeyCode::Right, MenuKey::Right),
+                (VirtualKeyCode::Return, MenuKey::Select),
+                (VirtualKeyCode::Escape, MenuKey::Back),
+                (VirtualKeyCode::Back, MenuKey::Back),
+            ];
+            for (code, key) in menu_keys.iter() {
+                if !input.key_pressed(*code) {
+                    continue;
+                }
+                let path = match menu.press(*key) {
+                    Some(MenuAction::Load(path)) => path,
+                    Some(action) => {
+                        if let Some(message) = game.apply(action) {
+                            overlay.message(message, Instant::now());
+                        }
+                        continue;
+                    }
+                    None => continue,
+                };
+                match Game::load(&path, &settings, &config) {
+                    Ok(loaded) => {
+                        game = loaded;
+                        if watch {
+                            watcher = watch_rom(&game.path);
+                        }
+                        window.set_title(&game.title);
+                        waiting_for_key = false;
+                        overlay.rom_name = game.name.clone();
+                        overlay.message(format!("Loaded {}", game.name), Instant::now());
+                        persistence = Persistence::new(persistence.filter());
+                        redraw_rows = ALL_ROWS;
+                    }
+                    Err(e) => {
+                        let message = format!("Failed to load {}: {}", path.display(), e);
+                        overlay.message(message, Instant::now());
+                    }
                 }
             }
 
-            interpreter.update_inputs(&input);
-            interpreter.update();
-            window.request_redraw();
+            // Keep the window at least one physical pixel per pixel on a new monitor
+            if let Some(hidpi_factor) = input.scale_factor_changed() {
+                let min_size: LogicalSize<f64> =
+                    PhysicalSize::new(WINDOW_WIDTH, WINDOW_HEIGHT).to_logical(hidpi_factor);
+                window.set_min_inner_size(Some(min_size));
+            }
+            // Fit the picture to the window, letterboxed, whatever its shape
+            if input.window_resized().is_some() || input.scale_factor_changed().is_some() {
+                let size = window.inner_size();
+                if size.width > 0 && size.height > 0 {
+                    pixels.resize_surface(size.width, size.height);
+                    crt.resize(size.width, size.height);
+                    window.request_redraw();
+                }
+            }
+
+            // Toggle fullscreen
+            if input.key_pressed(VirtualKeyCode::F11)
+                || (input.key_pressed(VirtualKeyCode::Return) && input.held_alt())
+            {
+                if window.fullscreen().is_some() {
+                    window.set_fullscreen(None);
+                } else {
+                    window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+                }
+            }
+
+            // Turn the CRT effects on and off
+            if input.key_pressed(VirtualKeyCode::F7) && !settings.effects.is_off() {
+                effects_on = !effects_on;
+                crt.set_effects(pixels.context(), gpu_effects(effects_on));
+                let state = if effects_on { "on" } else { "off" };
+                overlay.message(format!("Effects {}", state), Instant::now());
+                window.request_redraw();
+            }
+
+            // Step through the anti-flicker filters
+            if input.key_pressed(VirtualKeyCode::F8) {
+                persistence.set_filter(persistence.filter().next());
+                let message = format!("Filter: {}", persistence.filter().name());
+                overlay.message(message, Instant::now());
+            }
+
+            // Show the frame and instruction rates
+            if input.key_pressed(VirtualKeyCode::F1) {
+                overlay.show_stats = !overlay.show_stats;
+            }
+
+            // Start the game again
+            if input.key_pressed(VirtualKeyCode::F5) {
+                game.interpreter.reset();
+                overlay.message("Reset", Instant::now());
+            }
+
+            // Pause, step a frame or an instruction at a time, and change speed
+            if input.key_pressed(VirtualKeyCode::Pause) || input.key_pressed(VirtualKeyCode::F9) {
+                clock.set_paused(!clock.paused());
+                overlay.set_paused(clock.paused());
+            }
+            if input.key_pressed(VirtualKeyCode::F10) {
+                if input.held_shift() {
+                    clock.step_instruction();
+                } else {
+                    clock.step_frame();
+                }
+                overlay.set_paused(true);
+            }
+            let speed = if input.key_pressed(VirtualKeyCode::Equals) {
+                Some(clock.faster())
+            } else if input.key_pressed(VirtualKeyCode::Minus) {
+                Some(clock.slower())
+            } else {
+                None
+            };
+            if let Some(speed) = speed {
+                overlay.message(format!("Speed {}", format_speed(speed)), Instant::now());
+            }
+            clock.turbo = input.key_held(VirtualKeyCode::Tab);
+
+            // Save a screenshot, scaled to the window unless shift is held
+            if input.key_pressed(VirtualKeyCode::F12) {
+                let mut screenshot = Screenshot {
+                    palette: game.palette.clone(),
+                    ..Screenshot::default()
+                };
+                if !input.held_shift() {
+                    let size = window.inner_size();
+                    let viewport = Scaling::Integer
+                        .viewport((WINDOW_WIDTH, WINDOW_HEIGHT), (size.width, size.height));
+                    screenshot.scale = viewport.width as u32 / WINDOW_WIDTH;
+                }
+                let path = ImageFormat::Png.timestamped_path();
+                match screenshot.save(game.interpreter.planes(), &path) {
+                    Ok(()) => {
+                        println!("Saved screenshot to {}", path.display());
+                        overlay.message("Screenshot saved", Instant::now());
+                    }
+                    Err(e) => eprintln!("Failed to save screenshot: {}", e),
+                }
+            }
+
+            if !menu.is_open() {
+                game.interpreter.update_inputs(&input, &game.key_map);
+                game.gamepads.update(&mut game.interpreter.keypad);
+            }
+
+            // Catch up on every 60 Hz frame that has passed
+            if time.elapsed() >= FRAME_DURATION {
+                // Pick up a rebuilt ROM
+                if watcher.as_ref().is_some_and(RomWatcher::changed) {
+                    // Keep the old ROM running if the new one can't be used
+                    let reloaded = read(&game.path)
+                        .map_err(|e| e.to_string())
+                        .and_then(|bytes| game.interpreter.reload(bytes));
+                    let message = match reloaded {
+                        Ok(()) => format!("Reloaded {}", game.name),
+                        Err(e) => format!("Failed to reload {}: {}", game.name, e),
+                    };
+                    overlay.message(message, Instant::now());
+                }
+
+                // Only draw when the picture changed
+                let mut changed = false;
+                while time.elapsed() >= FRAME_DURATION {
+                    time += FRAME_DURATION;
+                    if menu.is_open() {
+                        continue;
+                    }
+                    let interpreter = &mut game.interpreter;
+                    let frames = clock.tick(interpreter);
+                    if frames > 0 {
+                        overlay.count_frames(frames, interpreter.instructions, Instant::now());
+                    }
+                    // Save states, new ROMs and single steps change the picture
+                    // while paused, but filters only move on with frames
+                    let dirty = interpreter.take_dirty_rows() != 0;
+                    if dirty || (frames > 0 && !persistence.is_settled()) {
+                        let rows = persistence.update(interpreter.planes());
+                        redraw_rows |= rows;
+                        changed |= rows != 0;
+                    }
+                }
+                overlay.menu = menu.lines();
+                if overlay.update(Instant::now()) {
+                    crt.set_overlay(pixels.context(), overlay.image());
+                    changed = true;
+                }
+                if changed {
+                    window.request_redraw();
+                }
+
+                // Tell the user when the game is waiting for them
+                if game.interpreter.waiting_for_key() != waiting_for_key {
+                    waiting_for_key = game.interpreter.waiting_for_key();
+                    if waiting_for_key {
+                        window.set_title(&format!("{} (waiting for input)", game.title));
+                    } else {
+                        window.set_title(&game.title);
+                    }
+                }
+            }
+            *control_flow = ControlFlow::WaitUntil(time + FRAME_DURATION);
         }
     });
 }
@@ -0,0 +1,82 @@
+use std::fs::{read_to_string, write};
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::display::effects::{Effects, Renderer};
+use crate::display::palette::Palette;
+use crate::display::persistence::Filter;
+use crate::display::scaling::Scaling;
+
+/// The settings file frontends look for in the working directory.
+pub const SETTINGS_FILE: &str = "settings.json";
+
+/// Display settings for the frontends.
+///
+/// ```json
+/// {
+///     "palette": "amber",
+///     "filter": "decay",
+///     "effects": { "scanlines": 0.5, "bloom": 0.3, "curvature": 0.1, "pixel_grid": 0 },
+///     "renderer": "gpu",
+///     "scaling": "integer",
+///     "fullscreen": false
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// A theme or `#rrggbb` colours as taken by `Palette::parse`. ROMs'
+    /// colours from the database are used if not given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub palette: Option<String>,
+    /// The anti-flicker filter to start with, off if not given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<Filter>,
+    /// CRT effects, off by default.
+    #[serde(skip_serializing_if = "Effects::is_off")]
+    pub effects: Effects,
+    /// Where to draw the effects. The CPU is slower but works without a
+    /// graphics card.
+    pub renderer: Renderer,
+    /// Whole or fractional window pixels per pixel.
+    pub scaling: Scaling,
+    /// Start in fullscreen.
+    pub fullscreen: bool,
+}
+
+impl Settings {
+    pub fn load(path: &Path) -> io::Result<Settings> {
+        let settings: Settings = serde_json::from_str(&read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if let Some(palette) = &settings.palette {
+            Palette::parse(palette).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        }
+        Ok(settings)
+    }
+
+    /// Load `SETTINGS_FILE` if there is one.
+    pub fn load_default() -> io::Result<Settings> {
+        let path = Path::new(SETTINGS_FILE);
+        if path.exists() {
+            Settings::load(path)
+        } else {
+            Ok(Settings::default())
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        write(path, serde_json::to_string_pretty(self)? + "\n")
+    }
+
+    /// The palette to draw in: this one if set, then `rom_palette`, then the
+    /// default.
+    pub fn palette(&self, rom_palette: Option<Palette>) -> Palette {
+        self.palette
+            .as_deref()
+            .and_then(|spec| Palette::parse(spec).ok())
+            .or(rom_palette)
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,301 @@
+/// What the CPU is wired to: memory, the I/O ports and the external flag inputs.
+pub trait Bus {
+    fn read(&mut self, address: u16) -> u8;
+    fn write(&mut self, address: u16, value: u8);
+    /// `OUT n`, ports 1 to 7.
+    fn output(&mut self, port: u8, value: u8);
+    /// `INP n`, ports 1 to 7.
+    fn input(&mut self, port: u8) -> u8;
+    /// Whether external flag EF1 to EF4 is asserted.
+    fn flag(&self, flag: u8) -> bool;
+}
+
+/// The RCA CDP1802 COSMAC microprocessor.
+///
+/// Cycle counts are in machine cycles of 8 clock pulses: 2 for most
+/// instructions, 3 for long branches and skips, and 1 for interrupts and each
+/// DMA byte.
+#[derive(Clone, Debug, Default)]
+pub struct Cdp1802 {
+    /// The sixteen 16-bit scratchpad registers R0 to RF.
+    pub registers: [u16; 16],
+    /// Which register is the program counter.
+    pub p: u8,
+    /// Which register is the data pointer.
+    pub x: u8,
+    /// The accumulator.
+    pub d: u8,
+    /// The carry/borrow flag.
+    pub df: bool,
+    /// X and P saved by an interrupt or MARK.
+    pub t: u8,
+    pub interrupt_enable: bool,
+    /// The Q output, which drives the VIP's tone generator.
+    pub q: bool,
+    /// Stopped by IDL until the next interrupt or DMA.
+    pub idle: bool,
+}
+
+impl Cdp1802 {
+    /// The state after the CLEAR input is pulsed: R0 as program counter at 0000.
+    pub fn reset(&mut self) {
+        self.registers[0] = 0;
+        self.p = 0;
+        self.x = 0;
+        self.q = false;
+        self.interrupt_enable = true;
+        self.idle = false;
+    }
+
+    /// Run one instruction, returning the machine cycles it took.
+    pub fn step<B: Bus>(&mut self, bus: &mut B) -> u32 {
+        if self.idle {
+            return 1;
+        }
+        let opcode = self.fetch(bus);
+        let n = (opcode & 0x0f) as usize;
+        match opcode >> 4 {
+            0x0 if n == 0 => self.idle = true,
+            0x0 => self.d = bus.read(self.registers[n]),
+            0x1 => self.registers[n] = self.registers[n].wrapping_add(1),
+            0x2 => self.registers[n] = self.registers[n].wrapping_sub(1),
+            0x3 => {
+                let taken = self.condition(n as u8 & 7, bus) != (n & 8 != 0);
+                let pc = self.registers[self.p as usize];
+                if taken {
+                    let target = bus.read(pc);
+                    self.registers[self.p as usize] = pc & 0xff00 | target as u16;
+                } else {
+                    self.registers[self.p as usize] = pc.wrapping_add(1);
+                }
+            }
+            0x4 => {
+                self.d = bus.read(self.registers[n]);
+                self.registers[n] = self.registers[n].wrapping_add(1);
+            }
+            0x5 => bus.write(self.registers[n], self.d),
+            0x6 => self.input_output(n as u8, bus),
+            0x7 => self.control(n as u8, bus),
+            0x8 => self.d = self.registers[n] as u8,
+            0x9 => self.d = (self.registers[n] >> 8) as u8,
+            0xa => self.registers[n] = self.registers[n] & 0xff00 | self.d as u16,
+            0xb => self.registers[n] = self.registers[n] & 0x00ff | (self.d as u16) << 8,
+            0xc => {
+                self.long_branch(n as u8, bus);
+                return 3;
+            }
+            0xd => self.p = n as u8,
+            0xe => self.x = n as u8,
+            _ => self.alu(n as u8, bus),
+        }
+        2
+    }
+
+    /// Take an interrupt if they're enabled, returning the machine cycles it took.
+    pub fn interrupt(&mut self) -> u32 {
+        if !self.interrupt_enable {
+            return 0;
+        }
+        self.t = self.x << 4 | self.p;
+        self.p = 1;
+        self.x = 2;
+        self.interrupt_enable = false;
+        self.idle = false;
+        1
+    }
+
+    /// One DMA output cycle: read the byte R0 points at and advance R0.
+    pub fn dma_out<B: Bus>(&mut self, bus: &mut B) -> u8 {
+        let value = bus.read(self.registers[0]);
+        self.registers[0] = self.registers[0].wrapping_add(1);
+        self.idle = false;
+        value
+    }
+
+    fn fetch<B: Bus>(&mut self, bus: &mut B) -> u8 {
+        let pc = &mut self.registers[self.p as usize];
+        let value = bus.read(*pc);
+        *pc = pc.wrapping_add(1);
+        value
+    }
+
+    fn data<B: Bus>(&mut self, bus: &mut B) -> u8 {
+        bus.read(self.registers[self.x as usize])
+    }
+
+    fn increment_x(&mut self) {
+        let x = self.x as usize;
+        self.registers[x] = self.registers[x].wrapping_add(1);
+    }
+
+    /// Branch conditions 0 to 7: always, Q, D zero, DF, then EF1 to EF4.
+    fn condition<B: Bus>(&self, condition: u8, bus: &B) -> bool {
+        match condition {
+            0 => true,
+            1 => self.q,
+            2 => self.d == 0,
+            3 => self.df,
+            flag => bus.flag(flag - 3),
+        }
+    }
+
+    /// 6N: IRX, OUT 1-7, then INP 1-7 from 69.
+    fn input_output<B: Bus>(&mut self, n: u8, bus: &mut B) {
+        match n {
+            0 => self.increment_x(),
+            1..=7 => {
+                let value = self.data(bus);
+                bus.output(n, value);
+                self.increment_x();
+            }
+            // 68 is unused on the 1802
+            8 => {}
+            _ => {
+                let value = bus.input(n - 8);
+                bus.write(self.registers[self.x as usize], value);
+                self.d = value;
+            }
+        }
+    }
+
+    /// 7N: returns, stack operations, Q and arithmetic with carry.
+    fn control<B: Bus>(&mut self, n: u8, bus: &mut B) {
+        match n {
+            0x0 | 0x1 => {
+                let value = self.data(bus);
+                self.increment_x();
+                self.x = value >> 4;
+                self.p = value & 0x0f;
+                self.interrupt_enable = n == 0;
+            }
+            0x2 => {
+                self.d = self.data(bus);
+                self.increment_x();
+            }
+            0x3 => {
+                let x = self.x as usize;
+                bus.write(self.registers[x], self.d);
+                self.registers[x] = self.registers[x].wrapping_sub(1);
+            }
+            0x4 => {
+                let value = self.data(bus);
+                self.add(value, self.d, self.df);
+            }
+            0x5 => {
+                let value = self.data(bus);
+                self.subtract(value, self.d, !self.df);
+            }
+            0x6 => {
+                let carry = self.d & 1 != 0;
+                self.d = self.d >> 1 | (self.df as u8) << 7;
+                self.df = carry;
+            }
+            0x7 => {
+                let value = self.data(bus);
+                self.subtract(self.d, value, !self.df);
+            }
+            0x8 => bus.write(self.registers[self.x as usize], self.t),
+            0x9 => {
+                self.t = self.x << 4 | self.p;
+                bus.write(self.registers[2], self.t);
+                self.x = self.p;
+                self.registers[2] = self.registers[2].wrapping_sub(1);
+            }
+            0xa => self.q = false,
+            0xb => self.q = true,
+            0xc => {
+                let value = self.fetch(bus);
+                self.add(value, self.d, self.df);
+            }
+            0xd => {
+                let value = self.fetch(bus);
+                self.subtract(value, self.d, !self.df);
+            }
+            0xe => {
+                let carry = self.d & 0x80 != 0;
+                self.d = self.d << 1 | self.df as u8;
+                self.df = carry;
+            }
+            _ => {
+                let value = self.fetch(bus);
+                self.subtract(self.d, value, !self.df);
+            }
+        }
+    }
+
+    /// CN: long branches, long skips and NOP.
+    fn long_branch<B: Bus>(&mut self, n: u8, bus: &mut B) {
+        let p = self.p as usize;
+        let pc = self.registers[p];
+        match n {
+            0x0..=0x3 | 0x8..=0xb => {
+                if self.condition(n & 3, bus) != (n & 8 != 0) {
+                    let high = bus.read(pc) as u16;
+                    let low = bus.read(pc.wrapping_add(1)) as u16;
+                    self.registers[p] = high << 8 | low;
+                } else {
+                    self.registers[p] = pc.wrapping_add(2);
+                }
+            }
+            0x4 => {}
+            _ => {
+                let skip = match n {
+                    0x5 => !self.q,
+                    0x6 => self.d != 0,
+                    0x7 => !self.df,
+                    0xc => self.interrupt_enable,
+                    0xd => self.q,
+                    0xe => self.d == 0,
+                    _ => self.df,
+                };
+                if skip {
+                    self.registers[p] = pc.wrapping_add(2);
+                }
+            }
+        }
+    }
+
+    /// FN: logic and arithmetic on M(RX), or on an immediate byte from F8.
+    fn alu<B: Bus>(&mut self, n: u8, bus: &mut B) {
+        match n {
+            0x6 => {
+                self.df = self.d & 1 != 0;
+                self.d >>= 1;
+                return;
+            }
+            0xe => {
+                self.df = self.d & 0x80 != 0;
+                self.d <<= 1;
+                return;
+            }
+            _ => {}
+        }
+        let value = if n < 8 {
+            self.data(bus)
+        } else {
+            self.fetch(bus)
+        };
+        match n & 7 {
+            0 => self.d = value,
+            1 => self.d |= value,
+            2 => self.d &= value,
+            3 => self.d ^= value,
+            4 => self.add(value, self.d, false),
+            5 => self.subtract(value, self.d, false),
+            _ => self.subtract(self.d, value, false),
+        }
+    }
+
+    fn add(&mut self, a: u8, b: u8, carry: bool) {
+        let sum = a as u16 + b as u16 + carry as u16;
+        self.d = sum as u8;
+        self.df = sum > 0xff;
+    }
+
+    /// DF is set when there was no borrow.
+    fn subtract(&mut self, a: u8, b: u8, borrow: bool) {
+        let difference = a as i16 - b as i16 - borrow as i16;
+        self.d = difference as u8;
+        self.df = difference >= 0;
+    }
+}
@@ -0,0 +1,107 @@
+/// Machine cycles in each line of the picture.
+pub const CYCLES_PER_LINE: u32 = 14;
+/// Lines in each 60 Hz frame, including the blanking interval.
+pub const LINES_PER_FRAME: u32 = 262;
+pub const CYCLES_PER_FRAME: u32 = CYCLES_PER_LINE * LINES_PER_FRAME;
+/// The first line fetched by DMA.
+pub const FIRST_DISPLAY_LINE: u32 = 80;
+/// Lines shown, each 64 pixels fetched as 8 DMA bytes.
+pub const DISPLAY_LINES: usize = 128;
+/// How long before the first DMA the interrupt is requested, so the interrupt
+/// routine can point R0 at the picture.
+pub const INTERRUPT_CYCLES: u32 = 29;
+
+/// The RCA CDP1861 video display controller.
+///
+/// It steals 8 DMA cycles from the CPU on each of 128 lines and raises EF1 for
+/// four lines either side of the end of the blanking interval and of the picture.
+#[derive(Clone, Debug)]
+pub struct Cdp1861 {
+    /// Turned on by `INP 1` and off by `OUT 1`.
+    enabled: bool,
+    /// Machine cycles since the start of the frame.
+    pub cycle: u32,
+    /// The lines fetched so far this frame, bit x for column x.
+    lines: [u64; DISPLAY_LINES],
+    /// The next line to fetch.
+    line: usize,
+    /// The last complete frame.
+    pub picture: [u64; DISPLAY_LINES],
+}
+
+impl Default for Cdp1861 {
+    fn default() -> Cdp1861 {
+        Cdp1861 {
+            enabled: false,
+            cycle: 0,
+            lines: [0; DISPLAY_LINES],
+            line: 0,
+            picture: [0; DISPLAY_LINES],
+        }
+    }
+}
+
+impl Cdp1861 {
+    /// Turn the display on or off. Lines already passed this frame stay blank.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if enabled && !self.enabled {
+            let line = self.cycle / CYCLES_PER_LINE;
+            if line >= FIRST_DISPLAY_LINE {
+                self.line = ((line - FIRST_DISPLAY_LINE + 1) as usize).min(DISPLAY_LINES);
+            }
+        }
+        self.enabled = enabled;
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Whether the interrupt request is active.
+    pub fn interrupt(&self) -> bool {
+        let first_dma = FIRST_DISPLAY_LINE * CYCLES_PER_LINE;
+        self.enabled && self.cycle + INTERRUPT_CYCLES >= first_dma && self.cycle < first_dma
+    }
+
+    /// EF1, which the interrupt routine polls to find the end of the picture.
+    pub fn status(&self) -> bool {
+        let line = self.cycle / CYCLES_PER_LINE;
+        let last = FIRST_DISPLAY_LINE + DISPLAY_LINES as u32;
+        (FIRST_DISPLAY_LINE - 4..FIRST_DISPLAY_LINE).contains(&line)
+            || (last - 4..last).contains(&line)
+    }
+
+    /// Whether the next line's DMA is due.
+    pub fn dma_due(&self) -> bool {
+        self.enabled
+            && self.line < DISPLAY_LINES
+            && self.cycle >= (FIRST_DISPLAY_LINE + self.line as u32) * CYCLES_PER_LINE
+    }
+
+    /// Store the bytes DMA fetched for the next line, leftmost pixel in the top bit.
+    pub fn store_line(&mut self, bytes: [u8; 8]) {
+        let mut line = 0;
+        for (column, byte) in bytes.iter().enumerate() {
+            line |= (byte.reverse_bits() as u64) << (column * 8);
+        }
+        self.lines[self.line] = line;
+        self.line += 1;
+    }
+
+    /// Advance the beam, returning true when a frame has finished.
+    pub fn advance(&mut self, cycles: u32) -> bool {
+        self.cycle += cycles;
+        if self.cycle < CYCLES_PER_FRAME {
+            return false;
+        }
+        self.cycle -= CYCLES_PER_FRAME;
+        if self.enabled {
+            self.picture = self.lines;
+        } else {
+            self.picture = [0; DISPLAY_LINES];
+        }
+        self.lines = [0; DISPLAY_LINES];
+        self.line = 0;
+        true
+    }
+}
@@ -0,0 +1,219 @@
+//! An RCA COSMAC VIP, running the original CHIP-8 interpreter on an emulated
+//! CDP1802 instead of our own high-level `Interpreter`.
+//!
+//! Neither the VIP's monitor ROM nor the CHIP-8 interpreter that shipped on
+//! cassette are bundled, so both have to be supplied as 512-byte images.
+
+pub mod cdp1802;
+pub mod cdp1861;
+
+use std::fmt;
+
+use self::cdp1802::{Bus, Cdp1802};
+use self::cdp1861::{Cdp1861, DISPLAY_LINES};
+use crate::constants::WINDOW_HEIGHT;
+
+/// The standard VIP's RAM, expanded to 4K, which the CHIP-8 interpreter needs
+/// to run programs of more than about 1K.
+pub const RAM_SIZE: usize = 4096;
+/// Size of the monitor ROM and of the CHIP-8 interpreter.
+pub const IMAGE_SIZE: usize = 512;
+/// Where CHIP-8 programs are loaded.
+pub const PROGRAM_START: usize = 0x200;
+
+/// Ways VIP images can be wrong.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VipError {
+    MonitorTooLarge(usize),
+    InterpreterTooLarge(usize),
+    ProgramTooLarge(usize),
+}
+
+impl fmt::Display for VipError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VipError::MonitorTooLarge(size) => write!(
+                f,
+                "monitor ROM is {} bytes, expected at most {}",
+                size, IMAGE_SIZE
+            ),
+            VipError::InterpreterTooLarge(size) => write!(
+                f,
+                "CHIP-8 interpreter is {} bytes, expected at most {}",
+                size, IMAGE_SIZE
+            ),
+            VipError::ProgramTooLarge(size) => write!(
+                f,
+                "program is {} bytes, expected at most {}",
+                size,
+                RAM_SIZE - PROGRAM_START
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VipError {}
+
+/// Everything on the VIP's board the CPU talks to.
+struct Board {
+    ram: [u8; RAM_SIZE],
+    rom: [u8; IMAGE_SIZE],
+    /// After a reset the ROM also answers at 0000, until the first access with
+    /// A15 set, so the monitor can start.
+    rom_low: bool,
+    video: Cdp1861,
+    /// The key selected by `OUT 2`, reported on EF3.
+    key_latch: u8,
+    keypad: [bool; 16],
+}
+
+impl Bus for Board {
+    fn read(&mut self, address: u16) -> u8 {
+        if address & 0x8000 != 0 {
+            self.rom_low = false;
+            return self.rom[address as usize % IMAGE_SIZE];
+        }
+        if self.rom_low {
+            return self.rom[address as usize % IMAGE_SIZE];
+        }
+        self.ram[address as usize % RAM_SIZE]
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        if address & 0x8000 != 0 {
+            self.rom_low = false;
+            return;
+        }
+        self.ram[address as usize % RAM_SIZE] = value;
+    }
+
+    fn output(&mut self, port: u8, value: u8) {
+        match port {
+            1 => self.video.set_enabled(false),
+            2 => self.key_latch = value & 0x0f,
+            _ => {}
+        }
+    }
+
+    fn input(&mut self, port: u8) -> u8 {
+        if port == 1 {
+            self.video.set_enabled(true);
+        }
+        0
+    }
+
+    fn flag(&self, flag: u8) -> bool {
+        match flag {
+            1 => self.video.status(),
+            3 => self.keypad[self.key_latch as usize],
+            _ => false,
+        }
+    }
+}
+
+/// A COSMAC VIP with 4K of RAM.
+pub struct Vip {
+    pub cpu: Cdp1802,
+    board: Board,
+    interpreter: [u8; IMAGE_SIZE],
+    pub rom: Vec<u8>,
+}
+
+impl Vip {
+    /// A VIP with `monitor` in ROM and the CHIP-8 `interpreter` at 0000.
+    pub fn new(monitor: &[u8], interpreter: &[u8]) -> Result<Vip, VipError> {
+        if monitor.len() > IMAGE_SIZE {
+            return Err(VipError::MonitorTooLarge(monitor.len()));
+        }
+        if interpreter.len() > IMAGE_SIZE {
+            return Err(VipError::InterpreterTooLarge(interpreter.len()));
+        }
+        let mut vip = Vip {
+            cpu: Cdp1802::default(),
+            board: Board {
+                ram: [0; RAM_SIZE],
+                rom: [0; IMAGE_SIZE],
+                rom_low: true,
+                video: Cdp1861::default(),
+                key_latch: 0,
+                keypad: [false; 16],
+            },
+            interpreter: [0; IMAGE_SIZE],
+            rom: Vec::new(),
+        };
+        vip.board.rom[..monitor.len()].copy_from_slice(monitor);
+        vip.interpreter[..interpreter.len()].copy_from_slice(interpreter);
+        vip.reset();
+        Ok(vip)
+    }
+
+    /// Load a CHIP-8 program and reset.
+    pub fn load_bytes(&mut self, bytes: Vec<u8>) -> Result<(), VipError> {
+        if bytes.len() > RAM_SIZE - PROGRAM_START {
+            return Err(VipError::ProgramTooLarge(bytes.len()));
+        }
+        self.rom = bytes;
+        self.reset();
+        Ok(())
+    }
+
+    /// Clear RAM, reload the interpreter and program, and flip the RUN switch.
+    ///
+    /// The monitor starts the program at 0000 unless C is held, so keep it up.
+    pub fn reset(&mut self) {
+        let board = &mut self.board;
+        board.ram = [0; RAM_SIZE];
+        board.ram[..IMAGE_SIZE].copy_from_slice(&self.interpreter);
+        board.ram[PROGRAM_START..PROGRAM_START + self.rom.len()].copy_from_slice(&self.rom);
+        board.rom_low = true;
+        board.video = Cdp1861::default();
+        board.key_latch = 0;
+        self.cpu.reset();
+    }
+
+    pub fn keypad(&mut self) -> &mut [bool; 16] {
+        &mut self.board.keypad
+    }
+
+    pub fn memory(&self) -> &[u8; RAM_SIZE] {
+        &self.board.ram
+    }
+
+    /// Run until the video chip finishes a frame.
+    pub fn update_frame(&mut self) {
+        loop {
+            let board = &mut self.board;
+            let cycles = if board.video.dma_due() {
+                let mut bytes = [0; 8];
+                for byte in bytes.iter_mut() {
+                    *byte = self.cpu.dma_out(board);
+                }
+                board.video.store_line(bytes);
+                bytes.len() as u32
+            } else if board.video.interrupt() && self.cpu.interrupt_enable {
+                self.cpu.interrupt()
+            } else {
+                self.cpu.step(board)
+            };
+            if board.video.advance(cycles) {
+                return;
+            }
+        }
+    }
+
+    /// The 128-line picture as 32 CHIP-8 rows. The interpreter shows each row
+    /// on four lines, so the first of each four is used.
+    pub fn video_output(&self) -> [u64; 32] {
+        let mut video_output = [0; 32];
+        let repeat = DISPLAY_LINES / WINDOW_HEIGHT as usize;
+        for (y, row) in video_output.iter_mut().enumerate() {
+            *row = self.board.video.picture[y * repeat];
+        }
+        video_output
+    }
+
+    /// Whether Q is driving the tone generator.
+    pub fn sound_active(&self) -> bool {
+        self.cpu.q
+    }
+}
@@ -0,0 +1,64 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration;
+
+use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// How long a file has to be left alone before a change counts, so a ROM
+/// written in pieces is reloaded once.
+pub const SETTLE_TIME: Duration = Duration::from_millis(200);
+
+/// Watches a ROM file so frontends can reload it when it's rebuilt.
+pub struct RomWatcher {
+    path: PathBuf,
+    events: Receiver<DebouncedEvent>,
+    /// Watching stops when this is dropped.
+    _watcher: RecommendedWatcher,
+}
+
+impl RomWatcher {
+    /// Watch the ROM at `path`. Its whole directory is watched, since many
+    /// editors and assemblers save by writing a new file and renaming it over
+    /// the old one.
+    pub fn new(path: &Path) -> io::Result<RomWatcher> {
+        let path = path.canonicalize()?;
+        let dir = path.parent().unwrap_or(&path).to_path_buf();
+        let (sender, events) = channel();
+        let mut watcher = watcher(sender, SETTLE_TIME).map_err(to_io_error)?;
+        watcher
+            .watch(&dir, RecursiveMode::NonRecursive)
+            .map_err(to_io_error)?;
+        Ok(RomWatcher {
+            path,
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    /// The ROM being watched, with symlinks resolved.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the ROM was written, created or renamed into place since the
+    /// last call. Never blocks.
+    pub fn changed(&self) -> bool {
+        self.events.try_iter().fold(false, |changed, event| {
+            let path = match event {
+                DebouncedEvent::Create(path)
+                | DebouncedEvent::Write(path)
+                | DebouncedEvent::Rename(_, path) => path,
+                _ => return changed,
+            };
+            changed || path == self.path
+        })
+    }
+}
+
+fn to_io_error(error: notify::Error) -> io::Error {
+    match error {
+        notify::Error::Io(error) => error,
+        error => io::Error::other(error.to_string()),
+    }
+}
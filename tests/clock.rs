@@ -0,0 +1,99 @@
+//! Pausing, stepping and changing the speed of the clock.
+
+use hoyer_chip_8::interpreter::clock::{format_speed, Clock, DEFAULT_TURBO_SPEED, SPEEDS};
+use hoyer_chip_8::interpreter::interpreter::Interpreter;
+use hoyer_chip_8::interpreter::timing::Timing;
+
+/// Counts V0 up forever, 10 instructions a frame, with the delay timer at 200.
+fn counter() -> Interpreter {
+    let mut interpreter = Interpreter::builder().program(&[0x7001, 0x1200]).build();
+    interpreter.timing = Timing::Instructions(10);
+    interpreter.delay_timer = 200;
+    interpreter
+}
+
+/// Tick `ticks` times, returning the frames run.
+fn tick(clock: &mut Clock, interpreter: &mut Interpreter, ticks: u32) -> u32 {
+    (0..ticks).map(|_| clock.tick(interpreter)).sum()
+}
+
+#[test]
+fn speed_steps_and_clamps() {
+    let mut clock = Clock::default();
+    assert_eq!(clock.speed(), 1.0);
+    assert_eq!(clock.faster(), 2.0);
+    for _ in 0..10 {
+        clock.faster();
+    }
+    assert_eq!(clock.speed(), SPEEDS[SPEEDS.len() - 1]);
+    for _ in 0..10 {
+        clock.slower();
+    }
+    assert_eq!(clock.speed(), 0.25);
+    clock.set_speed(100.0);
+    assert_eq!(clock.speed(), 16.0);
+    assert_eq!(format_speed(0.25), "0.25x");
+    assert_eq!(format_speed(2.0), "2x");
+}
+
+#[test]
+fn speed_changes_frames_per_tick() {
+    let mut interpreter = counter();
+    let mut clock = Clock::default();
+    assert_eq!(tick(&mut clock, &mut interpreter, 4), 4);
+    assert_eq!(interpreter.instructions, 40);
+    assert_eq!(interpreter.delay_timer, 196);
+
+    clock.set_speed(0.25);
+    assert_eq!(tick(&mut clock, &mut interpreter, 8), 2);
+    clock.set_speed(2.0);
+    assert_eq!(tick(&mut clock, &mut interpreter, 3), 6);
+
+    clock.turbo = true;
+    assert_eq!(clock.effective_speed(), DEFAULT_TURBO_SPEED);
+    assert_eq!(tick(&mut clock, &mut interpreter, 1), 8);
+    clock.turbo = false;
+    assert_eq!(tick(&mut clock, &mut interpreter, 1), 2);
+}
+
+#[test]
+fn paused_clock_only_steps() {
+    let mut interpreter = counter();
+    let mut clock = Clock::default();
+    clock.set_paused(true);
+    assert_eq!(tick(&mut clock, &mut interpreter, 10), 0);
+    assert_eq!(interpreter.instructions, 0);
+
+    clock.step_frame();
+    clock.step_frame();
+    assert_eq!(tick(&mut clock, &mut interpreter, 3), 2);
+    assert_eq!(interpreter.instructions, 20);
+    assert_eq!(interpreter.delay_timer, 198);
+}
+
+#[test]
+fn instruction_steps_finish_frames_as_they_go() {
+    let mut interpreter = counter();
+    let mut clock = Clock::default();
+    clock.step_instruction();
+    assert!(clock.paused());
+    assert_eq!(clock.tick(&mut interpreter), 0);
+    assert_eq!(interpreter.registers[0], 1);
+
+    // The tenth instruction ends the frame, the eleventh starts the next
+    for _ in 0..9 {
+        clock.step_instruction();
+    }
+    assert_eq!(clock.tick(&mut interpreter), 0);
+    assert_eq!(interpreter.delay_timer, 200);
+    clock.step_instruction();
+    assert_eq!(clock.tick(&mut interpreter), 1);
+    assert_eq!(interpreter.delay_timer, 199);
+    assert_eq!(interpreter.instructions, 11);
+
+    // A frame step runs the rest of the part-run frame
+    clock.step_frame();
+    clock.tick(&mut interpreter);
+    assert_eq!(interpreter.instructions, 20);
+    assert_eq!(interpreter.delay_timer, 198);
+}
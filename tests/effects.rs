@@ -0,0 +1,92 @@
+//! CRT effects, on the CPU and in the shader.
+
+use hoyer_chip_8::display::effects::{Effects, Renderer};
+use hoyer_chip_8::display::shader::CRT_SHADER;
+use hoyer_chip_8::settings::Settings;
+
+/// A 2x1 image, the left pixel white and the right black, drawn at 8x8 per pixel.
+fn render(effects: Effects) -> Vec<u8> {
+    let source = [0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0xff];
+    let mut target = vec![0; 16 * 8 * 4];
+    effects.apply(&source, (2, 1), &mut target, (16, 8));
+    target
+}
+
+/// Red at (x, y) of a 16 pixel wide image.
+fn red(image: &[u8], x: usize, y: usize) -> u8 {
+    image[(y * 16 + x) * 4]
+}
+
+#[test]
+fn shader_parses_and_validates() {
+    let module = naga::front::wgsl::parse_str(CRT_SHADER).unwrap();
+    naga::proc::Validator::new().validate(&module).unwrap();
+    let entry_points: Vec<&str> = module
+        .entry_points
+        .iter()
+        .map(|((_, name), _)| name.as_str())
+        .collect();
+    assert!(entry_points.contains(&"vs_main"), "{:?}", entry_points);
+    assert!(entry_points.contains(&"fs_main"), "{:?}", entry_points);
+}
+
+#[test]
+fn no_effects_just_scales() {
+    let image = render(Effects::default());
+    for y in 0..8 {
+        for x in 0..16 {
+            assert_eq!(
+                red(&image, x, y),
+                if x < 8 { 0xff } else { 0 },
+                "{} {}",
+                x,
+                y
+            );
+        }
+    }
+    assert!(image.chunks(4).all(|pixel| pixel[3] == 0xff));
+}
+
+#[test]
+fn scanlines_and_grid_darken_pixel_edges() {
+    let scanlines = render(Effects {
+        scanlines: 1.0,
+        ..Effects::default()
+    });
+    assert!(red(&scanlines, 4, 0) < 0x80);
+    assert!(red(&scanlines, 4, 4) > 0xf0);
+
+    let grid = render(Effects {
+        pixel_grid: 0.5,
+        ..Effects::default()
+    });
+    assert_eq!(red(&grid, 4, 4), 0xff);
+    assert_eq!(red(&grid, 0, 4), 0x80);
+}
+
+#[test]
+fn bloom_spreads_and_curvature_rounds_corners() {
+    let bloom = render(Effects {
+        bloom: 0.9,
+        ..Effects::default()
+    });
+    assert!(red(&bloom, 12, 4) > 0, "light bleeds into the dark pixel");
+
+    let curved = render(Effects {
+        curvature: 0.5,
+        ..Effects::default()
+    });
+    assert_eq!(red(&curved, 0, 0), 0, "the corner is off the glass");
+    assert_eq!(red(&curved, 4, 4), 0xff);
+}
+
+#[test]
+fn effects_come_from_settings() {
+    let settings: Settings = serde_json::from_str(
+        r#"{ "effects": { "scanlines": 0.5, "bloom": 0.3, "curvature": 0.1 }, "renderer": "cpu" }"#,
+    )
+    .unwrap();
+    assert_eq!(settings.effects, Effects::crt());
+    assert_eq!(settings.renderer, Renderer::Cpu);
+    assert!(Settings::default().effects.is_off());
+}
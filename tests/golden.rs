@@ -0,0 +1,139 @@
+//! Runs every bundled ROM headlessly and compares the final frame against a
+//! checked-in golden image in `tests/golden`.
+//!
+//! Key presses for a ROM are read from `tests/golden/<ROM>.keys` in the
+//! headless script format. Run with `UPDATE_GOLDEN=1` to rewrite the golden
+//! images after an intentional change.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use hoyer_chip_8::display::screenshot::{to_ascii, Screenshot};
+use hoyer_chip_8::headless::{Runner, Script, StopCondition, StopReason};
+
+const SEED: u64 = 0xc8;
+
+fn golden(rom: &str, frames: u32) {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let name = Path::new(rom).file_stem().unwrap().to_str().unwrap();
+    let golden_dir = root.join("tests").join("golden");
+
+    let mut runner = Runner::from_rom(root.join("roms").join(rom).to_str().unwrap()).unwrap();
+    runner.interpreter.seed(SEED);
+    if let Ok(text) = fs::read_to_string(golden_dir.join(format!("{}.keys", name))) {
+        runner.script = Script::parse(&text).unwrap();
+    }
+    runner.stop_conditions.push(StopCondition::Fault);
+    if let StopReason::Fault(fault) = runner.run(frames) {
+        panic!("{} faulted after {} frames: {}", rom, runner.frame, fault);
+    }
+
+    let planes = runner.interpreter.planes();
+    let mut actual = Vec::new();
+    Screenshot::default()
+        .write_pbm(planes, &mut actual)
+        .unwrap();
+
+    let expected_path = golden_dir.join(format!("{}.pbm", name));
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&expected_path, &actual).unwrap();
+        return;
+    }
+    let expected = fs::read(&expected_path).unwrap_or_else(|_| {
+        panic!(
+            "missing {}, run with UPDATE_GOLDEN=1 to create it",
+            expected_path.display()
+        )
+    });
+    if actual != expected {
+        let actual_path: PathBuf = root
+            .join("target")
+            .join("golden")
+            .join(format!("{}.pbm", name));
+        fs::create_dir_all(actual_path.parent().unwrap()).unwrap();
+        fs::write(&actual_path, &actual).unwrap();
+        panic!(
+            "{} doesn't match {}, the actual frame was saved to {}:\n{}",
+            rom,
+            expected_path.display(),
+            actual_path.display(),
+            to_ascii(planes)
+        );
+    }
+}
+
+/// Read the flags ROM's marks off the screen instead of trusting a golden
+/// image made by this interpreter: every check must show a tick.
+#[test]
+fn flags_rom_passes_every_check() {
+    const CHECK: [u8; 4] = [0x10, 0x20, 0xa0, 0x40];
+    // Checks in the rows for 8xy4, 8xy5, 8xy7, 8xy6 and 8xyE
+    const CHECKS: [u32; 5] = [5, 6, 6, 5, 5];
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut runner = Runner::from_rom(root.join("roms/test/flags.ch8").to_str().unwrap()).unwrap();
+    runner.run(60);
+    let screen = runner.interpreter.video_output;
+    for (row, checks) in CHECKS.iter().enumerate() {
+        let y = 2 + 6 * row;
+        for check in 0..*checks {
+            let x = 10 + 6 * check;
+            let mark: Vec<u8> = (0..4)
+                .map(|line| {
+                    (0..4).fold(0, |byte, bit| {
+                        byte | ((screen[y + line] >> (x + bit) & 1) as u8) << (7 - bit)
+                    })
+                })
+                .collect();
+            assert_eq!(mark, CHECK, "check {} of row {} failed", check + 1, row + 1);
+        }
+    }
+}
+
+macro_rules! golden_tests {
+    ($($name:ident: $rom:expr, $frames:expr;)*) => {
+        $(
+            #[test]
+            fn $name() {
+                golden($rom, $frames);
+            }
+        )*
+    };
+}
+
+golden_tests! {
+    test_opcode: "test/test_opcode.c8", 60;
+    flags: "test/flags.ch8", 60;
+    puzzle_15: "games/15PUZZLE.c8", 300;
+    blinky: "games/BLINKY.c8", 600;
+    blitz: "games/BLITZ.c8", 600;
+    brix: "games/BRIX.c8", 600;
+    connect4: "games/CONNECT4.c8", 300;
+    guess: "games/GUESS.c8", 300;
+    hidden: "games/HIDDEN.c8", 300;
+    invaders: "games/INVADERS.c8", 600;
+    kaleid: "games/KALEID.c8", 300;
+    maze: "games/MAZE.c8", 300;
+    merlin: "games/MERLIN.c8", 300;
+    missile: "games/MISSILE.c8", 600;
+    pong: "games/PONG.c8", 600;
+    pong2: "games/PONG2.c8", 600;
+    puzzle: "games/PUZZLE.c8", 300;
+    syzygy: "games/SYZYGY.c8", 600;
+    tank: "games/TANK.c8", 600;
+    tetris: "games/TETRIS.c8", 600;
+    tictac: "games/TICTAC.c8", 300;
+    ufo: "games/UFO.c8", 600;
+    vbrix: "games/VBRIX.c8", 600;
+    vers: "games/VERS.c8", 600;
+    wipeoff: "games/WIPEOFF.c8", 600;
+}
+
+#[test]
+fn unreadable_roms_are_errors() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let missing = root.join("roms").join("MISSING.c8");
+    assert!(Runner::from_rom(missing.to_str().unwrap()).is_err());
+    assert!(Runner::from_rom(root.join("roms").to_str().unwrap()).is_err());
+}
@@ -0,0 +1,185 @@
+//! Keyboard layouts, gamepads and the key configuration file.
+
+use std::collections::HashMap;
+use std::env::temp_dir;
+use std::fs::{remove_file, write};
+
+use hoyer_chip_8::database::RomDatabase;
+use hoyer_chip_8::input::gamepad::{Button, GamepadMap, GamepadState, JoystickEvent};
+use hoyer_chip_8::input::keymap::{virtual_key_code, KeyConfig, KeyMap, LAYOUTS};
+
+/// A `struct js_event` as the kernel sends it.
+fn js_event(kind: u8, number: u8, value: i16) -> [u8; 8] {
+    let mut bytes = [0; 8];
+    bytes[4..6].copy_from_slice(&value.to_ne_bytes());
+    bytes[6] = kind;
+    bytes[7] = number;
+    bytes
+}
+
+#[test]
+fn default_layout_matches_cosmac_keypad() {
+    let map = KeyMap::default();
+    let rows = ["1234", "qwer", "asdf", "zxcv"];
+    let keypad = [
+        [0x1, 0x2, 0x3, 0xc],
+        [0x4, 0x5, 0x6, 0xd],
+        [0x7, 0x8, 0x9, 0xe],
+        [0xa, 0x0, 0xb, 0xf],
+    ];
+    for (row, keys) in rows.iter().zip(keypad.iter()) {
+        for (name, key) in row.chars().zip(keys.iter()) {
+            assert_eq!(map.keypad_key(&name.to_string()), Some(*key), "{}", name);
+        }
+    }
+    assert_eq!(map.keypad_key("Q"), Some(0x4), "names ignore case");
+}
+
+#[test]
+fn every_layout_covers_the_keypad_with_real_keys() {
+    for name in LAYOUTS.iter() {
+        let map = KeyMap::layout(name).unwrap();
+        let mut covered = [false; 16];
+        for (key_name, key) in map.bindings() {
+            assert!(
+                virtual_key_code(key_name).is_some(),
+                "{} {}",
+                name,
+                key_name
+            );
+            covered[key as usize] = true;
+        }
+        assert_eq!(covered, [true; 16], "{}", name);
+    }
+}
+
+#[test]
+fn rom_bindings_apply_only_to_their_rom() {
+    let mut config = KeyConfig::default();
+    config.keys.insert(String::from("space"), 5);
+    config.bind_for_rom(b"PONG", "Up", 1);
+
+    let pong = config.key_map(b"PONG");
+    assert_eq!(pong.keypad_key("up"), Some(1));
+    assert_eq!(pong.keypad_key("space"), Some(5));
+    let other = config.key_map(b"BRIX");
+    assert_eq!(other.keypad_key("up"), None);
+    assert_eq!(other.keypad_key("space"), Some(5));
+}
+
+#[test]
+fn config_saves_and_loads() {
+    let path = temp_dir().join(format!("chip8-keys-{}.json", std::process::id()));
+    let mut config = KeyConfig {
+        layout: Some(String::from("dvorak")),
+        ..KeyConfig::default()
+    };
+    config.bind_for_rom(b"PONG", "left", 4);
+    config.save(&path).unwrap();
+    let loaded = KeyConfig::load(&path).unwrap();
+    assert_eq!(loaded, config);
+    assert_eq!(loaded.key_map(&[]).keypad_key("'"), Some(4));
+
+    write(&path, r#"{ "layout": "colemak" }"#).unwrap();
+    assert!(KeyConfig::load(&path).is_err());
+    remove_file(&path).unwrap();
+}
+
+#[test]
+fn joystick_events_parse() {
+    assert_eq!(
+        JoystickEvent::parse(js_event(0x01, 0, 1)),
+        Some(JoystickEvent::Button {
+            number: 0,
+            pressed: true
+        })
+    );
+    assert_eq!(
+        JoystickEvent::parse(js_event(0x82, 7, -32767)),
+        Some(JoystickEvent::Axis {
+            number: 7,
+            value: -32767
+        }),
+        "initial state events count too"
+    );
+    assert_eq!(JoystickEvent::parse(js_event(0x04, 0, 0)), None);
+}
+
+#[test]
+fn sticks_and_dpad_give_directions() {
+    let mut state = GamepadState::default();
+    let mut apply = |kind, number, value| {
+        state.apply(JoystickEvent::parse(js_event(kind, number, value)).unwrap());
+        state
+    };
+    assert!(apply(0x02, 1, -20000).held(Button::Up));
+    assert!(
+        !apply(0x02, 1, -8000).held(Button::Up),
+        "inside the dead zone"
+    );
+    assert!(apply(0x02, 6, 32767).held(Button::Right));
+    assert!(apply(0x01, 0, 1).held(Button::A));
+    let state = apply(0x01, 0, 0);
+    assert!(!state.held(Button::A));
+    assert!(!state.held(Button::Left));
+}
+
+#[test]
+fn pong2_maps_both_players_paddles() {
+    let rom = std::fs::read("roms/games/PONG2.c8").unwrap();
+    let info = RomDatabase::builtin().lookup(&rom).unwrap();
+    let player1 = GamepadMap::from_actions(&info.keys, 1);
+    let player2 = GamepadMap::from_actions(&info.keys, 2);
+    assert_eq!(player1.buttons.get(&Button::Up), Some(&0x1));
+    assert_eq!(player1.buttons.get(&Button::Down), Some(&0x4));
+    assert_eq!(player2.buttons.get(&Button::Up), Some(&0xc));
+    assert_eq!(player2.buttons.get(&Button::Down), Some(&0xd));
+
+    let mut state = GamepadState::default();
+    state.apply(JoystickEvent::Axis {
+        number: 7,
+        value: 32767,
+    });
+    let mut keypad = [false; 16];
+    keypad[0x1] = true;
+    state.press(&player2, &mut keypad);
+    assert!(keypad[0xd]);
+    assert!(keypad[0x1], "keys already held stay held");
+}
+
+#[test]
+fn gamepad_config_overrides_database() {
+    let mut config = KeyConfig::default();
+    let mut pong = HashMap::new();
+    pong.insert(String::from("a"), 0xf);
+    config
+        .gamepads
+        .insert(hoyer_chip_8::database::sha1_hex(b"PONG"), pong);
+    let mut defaults = HashMap::new();
+    defaults.insert(String::from("up"), 1);
+    defaults.insert(String::from("a"), 5);
+
+    let actions = config.gamepad_actions(b"PONG", &defaults);
+    assert_eq!(actions.get("up"), Some(&1));
+    assert_eq!(actions.get("a"), Some(&0xf));
+    assert_eq!(config.gamepad_actions(b"BRIX", &defaults), defaults);
+    assert_eq!(
+        GamepadMap::from_actions(&HashMap::new(), 1),
+        GamepadMap::standard()
+    );
+}
+
+#[test]
+fn player2_falls_back_to_player1() {
+    assert_eq!(
+        GamepadMap::from_actions(&HashMap::new(), 2),
+        GamepadMap::standard()
+    );
+    let mut actions = HashMap::new();
+    actions.insert(String::from("left"), 4);
+    actions.insert(String::from("right"), 6);
+    assert_eq!(
+        GamepadMap::from_actions(&actions, 2),
+        GamepadMap::from_actions(&actions, 1)
+    );
+}
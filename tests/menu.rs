@@ -0,0 +1,222 @@
+//! The in-window menu, the ROM browser's listing and save states.
+
+use std::env::temp_dir;
+use std::fs::{create_dir_all, remove_dir_all, write};
+use std::path::{Path, PathBuf};
+
+use hoyer_chip_8::database::library::{blurb, list_roms, RomEntry, ROM_DIR};
+use hoyer_chip_8::database::RomDatabase;
+use hoyer_chip_8::display::menu::{wrap, Menu, MenuAction, MenuKey};
+use hoyer_chip_8::interpreter::interpreter::Interpreter;
+use hoyer_chip_8::interpreter::memory::Ram;
+use hoyer_chip_8::interpreter::quirks::{Quirks, PROFILES};
+use hoyer_chip_8::interpreter::snapshot::{save_path, Snapshot};
+use hoyer_chip_8::interpreter::state::CpuState;
+use hoyer_chip_8::interpreter::timing::Timing;
+
+fn entry(name: &str) -> RomEntry {
+    RomEntry {
+        path: PathBuf::from(name),
+        info: None,
+        blurb: None,
+    }
+}
+
+fn open_menu() -> Menu {
+    let mut menu = Menu::new(vec![entry("A.ch8"), entry("B.ch8")], "roms");
+    menu.open(Timing::Instructions(15), Quirks::default());
+    menu
+}
+
+/// Press `key` `times` times, returning the last action.
+fn press(menu: &mut Menu, key: MenuKey, times: usize) -> Option<MenuAction> {
+    (0..times).map(|_| menu.press(key)).last().flatten()
+}
+
+#[test]
+fn quirk_profiles_have_names() {
+    for name in PROFILES.iter() {
+        let quirks = Quirks::profile(name).unwrap();
+        assert_eq!(quirks.profile_name(), Some(*name));
+    }
+    let mut custom = Quirks::chip8();
+    custom.shift = true;
+    assert_eq!(custom.profile_name(), None);
+}
+
+#[test]
+fn xochip_matches_the_database_platform() {
+    let mut quirks = Quirks::chip8();
+    for (name, value) in [
+        ("shift", false),
+        ("memoryIncrementByX", false),
+        ("memoryLeaveIUnchanged", false),
+        ("wrap", true),
+        ("jump", false),
+        ("vblank", false),
+        ("logic", false),
+    ]
+    .iter()
+    {
+        quirks.set(name, *value);
+    }
+    quirks.large_sprites = true;
+    assert_eq!(quirks, Quirks::xochip());
+    assert_eq!(Quirks::for_platform("xochip"), Some(quirks));
+}
+
+#[test]
+fn chip48_has_the_database_quirks() {
+    let quirks = Quirks::for_platform("chip48").unwrap();
+    assert!(quirks.shift && quirks.jump && quirks.memory_increment_by_x);
+    assert!(!quirks.memory_leave_i_unchanged && !quirks.large_sprites);
+    assert!(!quirks.logic && !quirks.vblank && !quirks.wrap);
+}
+
+#[test]
+fn menu_changes_settings_and_saves() {
+    let mut menu = open_menu();
+    assert_eq!(menu.press(MenuKey::Up), None);
+    assert_eq!(menu.lines()[9].trim(), "> Load state from slot 1");
+
+    // Speed, quirks and slot step with the arrows
+    let speed = press(&mut menu, MenuKey::Down, 4);
+    assert_eq!(speed, None);
+    assert!(menu.lines()[5].starts_with("> Speed   < 15 per frame >"));
+    assert_eq!(
+        menu.press(MenuKey::Right),
+        Some(MenuAction::Timing(Timing::Instructions(20)))
+    );
+    menu.press(MenuKey::Down);
+    assert_eq!(
+        menu.press(MenuKey::Left),
+        Some(MenuAction::Quirks(Quirks::profile(PROFILES[4]).unwrap()))
+    );
+    menu.press(MenuKey::Down);
+    assert_eq!(menu.press(MenuKey::Left), None);
+    assert_eq!(menu.slot, 4);
+
+    menu.press(MenuKey::Down);
+    assert_eq!(menu.press(MenuKey::Select), Some(MenuAction::SaveState(4)));
+    assert!(!menu.is_open());
+    assert!(menu.lines().is_empty());
+    assert_eq!(menu.press(MenuKey::Select), None, "closed");
+}
+
+#[test]
+fn browser_loads_a_rom() {
+    let mut menu = open_menu();
+    menu.press(MenuKey::Down);
+    menu.press(MenuKey::Select);
+    let lines = menu.lines();
+    assert_eq!(lines[0].trim(), "Load ROM from roms");
+    assert!(lines[2].starts_with("> A.ch8         A.ch8"), "{:?}", lines);
+    assert!(lines[3].contains("Not in the database"), "{:?}", lines);
+    assert!(lines.iter().all(|line| line.len() == lines[0].len()));
+
+    menu.press(MenuKey::Up);
+    assert_eq!(
+        menu.press(MenuKey::Select),
+        Some(MenuAction::Load(PathBuf::from("B.ch8")))
+    );
+
+    // Back goes to the main page, then closes
+    menu.open(Timing::CosmacVip, Quirks::chip8());
+    menu.press(MenuKey::Down);
+    menu.press(MenuKey::Select);
+    assert_eq!(menu.press(MenuKey::Back), None);
+    assert_eq!(menu.press(MenuKey::Back), Some(MenuAction::Resume));
+}
+
+#[test]
+fn text_wraps_between_words() {
+    assert_eq!(wrap("one two three", 7), ["one two", "three"]);
+    assert_eq!(wrap("abcdefghij", 4), ["abcd", "efgh", "ij"]);
+    assert_eq!(wrap("", 4), [""]);
+}
+
+#[test]
+fn bundled_roms_are_listed_from_the_database() {
+    let roms = list_roms(Path::new(ROM_DIR), &RomDatabase::builtin()).unwrap();
+    let names: Vec<String> = roms.iter().map(RomEntry::file_name).collect();
+    assert_eq!(names.first().map(String::as_str), Some("15PUZZLE.c8"));
+    assert!(!names.iter().any(|name| name == "README.md"));
+    let blinky = roms
+        .iter()
+        .find(|rom| rom.file_name() == "BLINKY.c8")
+        .unwrap();
+    assert_eq!(blinky.title(), "Blinky");
+}
+
+#[test]
+fn blurbs_come_from_text_files_or_the_readme() {
+    let dir = temp_dir().join(format!("chip8-roms-{}", std::process::id()));
+    create_dir_all(&dir).unwrap();
+    write(dir.join("PONG.ch8"), [0x12, 0x00]).unwrap();
+    write(dir.join("PONG.txt"), "\nBat and ball.\nFor two.\n\nMore.").unwrap();
+    write(dir.join("MAZE.ch8"), [0x12, 0x00]).unwrap();
+    write(
+        dir.join("README.md"),
+        "# ROMs\n\nCredits.\n\n## Maze.ch8\n\nDraws a maze.\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        blurb(&dir.join("PONG.ch8")).as_deref(),
+        Some("Bat and ball. For two.")
+    );
+    assert_eq!(
+        blurb(&dir.join("MAZE.ch8")).as_deref(),
+        Some("Draws a maze.")
+    );
+    let roms = list_roms(&dir, &RomDatabase::builtin()).unwrap();
+    assert_eq!(roms.len(), 2);
+    remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn save_states_restore_the_machine() {
+    let mut interpreter = Interpreter::builder()
+        .program(&[0x6005, 0xa250, 0xd015, 0x1206])
+        .build();
+    for _ in 0..4 {
+        interpreter.update();
+    }
+    let snapshot = interpreter.snapshot();
+
+    let path = temp_dir().join(format!("chip8-saves-{}", std::process::id()));
+    let file = save_path(&path, &interpreter.rom, 2);
+    assert!(file.to_string_lossy().ends_with("-2.json"));
+    snapshot.save(&file).unwrap();
+    assert_eq!(Snapshot::load(&file).unwrap(), snapshot);
+    remove_dir_all(&path).unwrap();
+
+    let mut restored = Interpreter::builder()
+        .program(&[0x6005, 0xa250, 0xd015, 0x1206])
+        .build();
+    restored.take_dirty_rows();
+    restored.restore(&snapshot).unwrap();
+    assert_eq!(restored.registers[0], 5);
+    assert_eq!(restored.index, 0x250);
+    assert_eq!(restored.video_output, interpreter.video_output);
+    assert_eq!(restored.program_counter, 0x206);
+    assert_ne!(restored.take_dirty_rows(), 0);
+
+    let mut larger = Interpreter::builder()
+        .memory(Box::new(Ram::new(65536)))
+        .build();
+    assert!(larger.restore(&snapshot).is_err());
+
+    // Edited save states can't break the interpreter
+    let mut overflowing = snapshot.clone();
+    overflowing.stack_pointer = 17;
+    assert!(restored.restore(&overflowing).is_err());
+    let mut waiting = snapshot.clone();
+    waiting.state = CpuState::WaitingForKey {
+        vx: 16,
+        held: [false; 16],
+        pressed: None,
+    };
+    assert!(restored.restore(&waiting).is_err());
+    assert_eq!(restored.program_counter, 0x206, "left as it was");
+}
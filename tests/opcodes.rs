@@ -0,0 +1,1063 @@
+//! One or more tests per opcode handler in `interpreter/execute.rs`, run against
+//! every quirk profile unless the opcode's behaviour depends on a quirk.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use hoyer_chip_8::database::{sha1_hex, RomDatabase};
+use hoyer_chip_8::display::palette::Palette;
+use hoyer_chip_8::interpreter::builder::InterpreterBuilder;
+use hoyer_chip_8::interpreter::code_map::SelfModification;
+use hoyer_chip_8::interpreter::fault::Fault;
+use hoyer_chip_8::interpreter::interpreter::{Interpreter, ALL_ROWS};
+use hoyer_chip_8::interpreter::memory::{self, Access, Ram};
+use hoyer_chip_8::interpreter::quirks::Quirks;
+use hoyer_chip_8::interpreter::timing::{vip_cycles, Timing, VIP_FETCH_CYCLES};
+
+fn profiles() -> [(&'static str, Quirks); 5] {
+    [
+        ("default", Quirks::default()),
+        ("chip8", Quirks::chip8()),
+        ("schip", Quirks::schip()),
+        ("modern", Quirks::modern()),
+        ("xochip", Quirks::xochip()),
+    ]
+}
+
+/// Run `opcode` once on an interpreter set up by `setup`, for every quirk profile.
+fn each_profile<S, C>(opcode: u16, setup: S, check: C)
+where
+    S: Fn(InterpreterBuilder) -> InterpreterBuilder,
+    C: Fn(&str, Quirks, &Interpreter),
+{
+    for (name, quirks) in profiles().iter() {
+        let interpreter = run(opcode, *quirks, &setup);
+        check(name, *quirks, &interpreter);
+    }
+}
+
+/// Run `opcode` once on an interpreter set up by `setup`.
+fn run<S>(opcode: u16, quirks: Quirks, setup: S) -> Interpreter
+where
+    S: Fn(InterpreterBuilder) -> InterpreterBuilder,
+{
+    let mut interpreter = setup(Interpreter::builder().program(&[opcode]).quirks(quirks)).build();
+    interpreter.update();
+    assert_eq!(interpreter.fault, None, "{:04X} faulted", opcode);
+    interpreter
+}
+
+fn run_default<S>(opcode: u16, setup: S) -> Interpreter
+where
+    S: Fn(InterpreterBuilder) -> InterpreterBuilder,
+{
+    run(opcode, Quirks::default(), setup)
+}
+
+#[test]
+fn clear_display() {
+    each_profile(
+        0x00e0,
+        |b| b.video_output([u64::MAX; 32]),
+        |name, _, i| {
+            assert_eq!(i.video_output, [0; 32], "{}", name);
+            assert_eq!(i.program_counter, 0x202, "{}", name);
+        },
+    );
+}
+
+#[test]
+fn return_from_subroutine() {
+    each_profile(
+        0x00ee,
+        |b| b.stack(&[0x300, 0x400]),
+        |name, _, i| {
+            assert_eq!(i.program_counter, 0x400, "{}", name);
+            assert_eq!(i.stack_pointer, 1, "{}", name);
+        },
+    );
+}
+
+#[test]
+fn return_with_empty_stack_faults() {
+    let mut interpreter = Interpreter::builder().program(&[0x00ee]).build();
+    interpreter.update();
+    assert_eq!(interpreter.fault, Some(Fault::StackUnderflow));
+}
+
+#[test]
+fn system_call_is_ignored() {
+    let interpreter = run_default(0x0123, |b| b);
+    assert_eq!(interpreter.program_counter, 0x202);
+}
+
+#[test]
+fn jump_to_address() {
+    each_profile(
+        0x1abc,
+        |b| b,
+        |name, _, i| assert_eq!(i.program_counter, 0xabc, "{}", name),
+    );
+}
+
+#[test]
+fn call_subroutine() {
+    each_profile(
+        0x2abc,
+        |b| b,
+        |name, _, i| {
+            assert_eq!(i.program_counter, 0xabc, "{}", name);
+            assert_eq!(i.stack_pointer, 1, "{}", name);
+            assert_eq!(i.stack[0], 0x202, "{}", name);
+        },
+    );
+}
+
+#[test]
+fn call_with_full_stack_faults() {
+    let mut interpreter = Interpreter::builder()
+        .program(&[0x2abc])
+        .stack(&[0x200; 16])
+        .build();
+    interpreter.update();
+    assert_eq!(interpreter.fault, Some(Fault::StackOverflow));
+}
+
+#[test]
+fn skip_if_vx_equal_nn() {
+    each_profile(
+        0x3342,
+        |b| b.register(3, 0x42),
+        |name, _, i| assert_eq!(i.program_counter, 0x204, "{}", name),
+    );
+    each_profile(
+        0x3342,
+        |b| b.register(3, 0x41),
+        |name, _, i| assert_eq!(i.program_counter, 0x202, "{}", name),
+    );
+}
+
+#[test]
+fn skip_if_vx_not_equal_nn() {
+    each_profile(
+        0x4342,
+        |b| b.register(3, 0x41),
+        |name, _, i| assert_eq!(i.program_counter, 0x204, "{}", name),
+    );
+    each_profile(
+        0x4342,
+        |b| b.register(3, 0x42),
+        |name, _, i| assert_eq!(i.program_counter, 0x202, "{}", name),
+    );
+}
+
+#[test]
+fn skip_if_vx_equal_vy() {
+    each_profile(
+        0x5120,
+        |b| b.registers(&[0, 7, 7]),
+        |name, _, i| assert_eq!(i.program_counter, 0x204, "{}", name),
+    );
+    each_profile(
+        0x5120,
+        |b| b.registers(&[0, 7, 8]),
+        |name, _, i| assert_eq!(i.program_counter, 0x202, "{}", name),
+    );
+}
+
+#[test]
+fn vx_set_nn() {
+    each_profile(
+        0x6a42,
+        |b| b,
+        |name, _, i| assert_eq!(i.registers[0xa], 0x42, "{}", name),
+    );
+}
+
+#[test]
+fn vx_add_nn_wraps_without_touching_vf() {
+    each_profile(
+        0x7102,
+        |b| b.register(1, 0xff).register(0xf, 0x55),
+        |name, _, i| {
+            assert_eq!(i.registers[1], 0x01, "{}", name);
+            assert_eq!(i.registers[0xf], 0x55, "{}", name);
+        },
+    );
+}
+
+#[test]
+fn vx_set_vy() {
+    each_profile(
+        0x8120,
+        |b| b.registers(&[0, 1, 2]),
+        |name, _, i| assert_eq!(i.registers[1], 2, "{}", name),
+    );
+}
+
+#[test]
+fn logic_ops() {
+    for (opcode, expected) in [(0x8121, 0b1110), (0x8122, 0b1000), (0x8123, 0b0110)].iter() {
+        each_profile(
+            *opcode,
+            |b| b.registers(&[0, 0b1100, 0b1010]).register(0xf, 0x55),
+            |name, quirks, i| {
+                assert_eq!(i.registers[1], *expected, "{:04X} {}", opcode, name);
+                let vf = if quirks.logic { 0 } else { 0x55 };
+                assert_eq!(i.registers[0xf], vf, "{:04X} {}", opcode, name);
+            },
+        );
+    }
+}
+
+#[test]
+fn vx_add_vy() {
+    each_profile(
+        0x8124,
+        |b| b.registers(&[0, 0xf0, 0x20]),
+        |name, _, i| {
+            assert_eq!(i.registers[1], 0x10, "{}", name);
+            assert_eq!(i.registers[0xf], 1, "{}", name);
+        },
+    );
+    each_profile(
+        0x8124,
+        |b| b.registers(&[0, 0x10, 0x20]).register(0xf, 1),
+        |name, _, i| {
+            assert_eq!(i.registers[1], 0x30, "{}", name);
+            assert_eq!(i.registers[0xf], 0, "{}", name);
+        },
+    );
+}
+
+#[test]
+fn vx_add_vy_with_vf_as_vx_keeps_carry() {
+    each_profile(
+        0x8f14,
+        |b| b.register(1, 0x01).register(0xf, 0xff),
+        |name, _, i| assert_eq!(i.registers[0xf], 1, "{}", name),
+    );
+}
+
+#[test]
+fn vx_add_vy_with_vf_as_vy_uses_old_vf() {
+    each_profile(
+        0x81f4,
+        |b| b.register(1, 0x01).register(0xf, 0xff),
+        |name, _, i| {
+            assert_eq!(i.registers[1], 0x00, "{}", name);
+            assert_eq!(i.registers[0xf], 1, "{}", name);
+        },
+    );
+}
+
+#[test]
+fn vx_sub_vy() {
+    each_profile(
+        0x8125,
+        |b| b.registers(&[0, 0x30, 0x10]),
+        |name, _, i| {
+            assert_eq!(i.registers[1], 0x20, "{}", name);
+            assert_eq!(i.registers[0xf], 1, "{}", name);
+        },
+    );
+    each_profile(
+        0x8125,
+        |b| b.registers(&[0, 0x10, 0x30]).register(0xf, 1),
+        |name, _, i| {
+            assert_eq!(i.registers[1], 0xe0, "{}", name);
+            assert_eq!(i.registers[0xf], 0, "{}", name);
+        },
+    );
+}
+
+#[test]
+fn vx_sub_vy_when_equal_does_not_borrow() {
+    each_profile(
+        0x8125,
+        |b| b.registers(&[0, 0x10, 0x10]),
+        |name, _, i| {
+            assert_eq!(i.registers[1], 0, "{}", name);
+            assert_eq!(i.registers[0xf], 1, "{}", name);
+        },
+    );
+}
+
+#[test]
+fn vx_sub_vy_with_vf_as_vx_keeps_flag() {
+    each_profile(
+        0x8f15,
+        |b| b.register(1, 0x01).register(0xf, 0x10),
+        |name, _, i| assert_eq!(i.registers[0xf], 1, "{}", name),
+    );
+}
+
+#[test]
+fn vx_subn_vy() {
+    each_profile(
+        0x8127,
+        |b| b.registers(&[0, 0x10, 0x30]),
+        |name, _, i| {
+            assert_eq!(i.registers[1], 0x20, "{}", name);
+            assert_eq!(i.registers[0xf], 1, "{}", name);
+        },
+    );
+    each_profile(
+        0x8127,
+        |b| b.registers(&[0, 0x30, 0x10]).register(0xf, 1),
+        |name, _, i| {
+            assert_eq!(i.registers[1], 0xe0, "{}", name);
+            assert_eq!(i.registers[0xf], 0, "{}", name);
+        },
+    );
+}
+
+#[test]
+fn vx_subn_vy_when_equal_does_not_borrow() {
+    each_profile(
+        0x8127,
+        |b| b.registers(&[0, 0x10, 0x10]),
+        |name, _, i| {
+            assert_eq!(i.registers[1], 0, "{}", name);
+            assert_eq!(i.registers[0xf], 1, "{}", name);
+        },
+    );
+}
+
+#[test]
+fn vx_shift_right() {
+    each_profile(
+        0x8126,
+        |b| b.registers(&[0, 0b0000_0101, 0b1000_0010]),
+        |name, quirks, i| {
+            if quirks.shift {
+                assert_eq!(i.registers[1], 0b0000_0010, "{}", name);
+                assert_eq!(i.registers[0xf], 1, "{}", name);
+            } else {
+                assert_eq!(i.registers[1], 0b0100_0001, "{}", name);
+                assert_eq!(i.registers[0xf], 0, "{}", name);
+            }
+        },
+    );
+}
+
+#[test]
+fn vx_shift_left() {
+    each_profile(
+        0x812e,
+        |b| b.registers(&[0, 0b1000_0001, 0b0100_0000]),
+        |name, quirks, i| {
+            if quirks.shift {
+                assert_eq!(i.registers[1], 0b0000_0010, "{}", name);
+                assert_eq!(i.registers[0xf], 1, "{}", name);
+            } else {
+                assert_eq!(i.registers[1], 0b1000_0000, "{}", name);
+                assert_eq!(i.registers[0xf], 0, "{}", name);
+            }
+        },
+    );
+}
+
+#[test]
+fn shifts_with_vf_as_vx_keep_flag() {
+    for opcode in [0x8ff6, 0x8ffe].iter() {
+        each_profile(
+            *opcode,
+            |b| b.register(0xf, 0b1000_0001),
+            |name, _, i| assert_eq!(i.registers[0xf], 1, "{:04X} {}", opcode, name),
+        );
+    }
+}
+
+#[test]
+fn skip_if_vx_not_equal_vy() {
+    each_profile(
+        0x9120,
+        |b| b.registers(&[0, 7, 8]),
+        |name, _, i| assert_eq!(i.program_counter, 0x204, "{}", name),
+    );
+    each_profile(
+        0x9120,
+        |b| b.registers(&[0, 7, 7]),
+        |name, _, i| assert_eq!(i.program_counter, 0x202, "{}", name),
+    );
+}
+
+#[test]
+fn index_set_nnn() {
+    each_profile(
+        0xa123,
+        |b| b,
+        |name, _, i| assert_eq!(i.index, 0x123, "{}", name),
+    );
+}
+
+#[test]
+fn jump_with_offset() {
+    each_profile(
+        0xb310,
+        |b| b.registers(&[0x02, 0, 0, 0x04]),
+        |name, quirks, i| {
+            let expected = if quirks.jump { 0x314 } else { 0x312 };
+            assert_eq!(i.program_counter, expected, "{}", name);
+        },
+    );
+}
+
+#[test]
+fn vx_set_rand_and_nn() {
+    let first = run_default(0xc1ff, |b| b.seed(7)).registers[1];
+    let second = run_default(0xc1ff, |b| b.seed(7)).registers[1];
+    assert_eq!(first, second);
+
+    for seed in 0..32 {
+        let interpreter = run_default(0xc10f, |b| b.seed(seed));
+        assert_eq!(interpreter.registers[1] & 0xf0, 0);
+    }
+}
+
+#[test]
+fn display_sprite() {
+    each_profile(
+        0xd125,
+        |b| b.registers(&[0, 8, 4]).index(0x50),
+        |name, _, i| {
+            // The font's 0 is F0 90 90 90 F0, drawn with its left edge at x = 8
+            let rows: Vec<u64> = i.video_output[4..9].to_vec();
+            assert_eq!(rows, [0xf00, 0x900, 0x900, 0x900, 0xf00], "{}", name);
+            assert_eq!(i.registers[0xf], 0, "{}", name);
+        },
+    );
+}
+
+#[test]
+fn display_sprite_wraps_or_clips_at_edges() {
+    // A 2x2 block drawn at (63, 31) only fits its top left pixel on screen
+    each_profile(
+        0xd122,
+        |b| {
+            b.registers(&[0, 63, 31])
+                .index(0x300)
+                .memory_at(0x300, &[0xc0, 0xc0])
+        },
+        |name, quirks, i| {
+            let mut expected = [0; 32];
+            expected[31] = 1 << 63;
+            if quirks.wrap {
+                expected[31] |= 1;
+                expected[0] = 1 << 63 | 1;
+            }
+            assert_eq!(i.video_output, expected, "{}", name);
+        },
+    );
+}
+
+#[test]
+fn display_sprite_large() {
+    let sprite: Vec<u8> = (0..16).flat_map(|row| [0x80, row as u8]).collect();
+    each_profile(
+        0xd120,
+        |b| b.index(0x300).memory_at(0x300, &sprite),
+        |name, quirks, i| {
+            if quirks.large_sprites {
+                for row in 0..16 {
+                    let mut expected = 1;
+                    for bit in 0..8 {
+                        if row & (1 << bit) != 0 {
+                            expected |= 1 << (15 - bit);
+                        }
+                    }
+                    assert_eq!(i.video_output[row], expected, "{} row {}", name, row);
+                }
+            } else {
+                assert_eq!(i.video_output, [0; 32], "{}", name);
+            }
+        },
+    );
+}
+
+#[test]
+fn display_sprite_on_both_planes() {
+    let mut interpreter = Interpreter::builder()
+        .program(&[0xf301, 0xd121])
+        .index(0x300)
+        .memory_at(0x300, &[0x80, 0x40])
+        .build();
+    interpreter.second_plane[0] = 1 << 1;
+    interpreter.update();
+    interpreter.update();
+    assert_eq!(interpreter.video_output[0], 1);
+    assert_eq!(interpreter.second_plane[0], 0);
+    assert_eq!(interpreter.registers[0xf], 1);
+}
+
+#[test]
+fn clear_display_only_clears_selected_planes() {
+    let mut interpreter = Interpreter::builder()
+        .program(&[0xf201, 0x00e0])
+        .video_output([1; 32])
+        .build();
+    interpreter.second_plane = [1; 32];
+    interpreter.update();
+    interpreter.update();
+    assert_eq!(interpreter.video_output, [1; 32]);
+    assert_eq!(interpreter.second_plane, [0; 32]);
+}
+
+#[test]
+fn display_changes_mark_dirty_rows() {
+    let mut interpreter = Interpreter::builder()
+        .program(&[0xd125, 0xd125, 0x00e0, 0x00e0])
+        .registers(&[0, 8, 4])
+        .index(0x50)
+        .build();
+    assert_eq!(interpreter.take_dirty_rows(), ALL_ROWS);
+    assert_eq!(interpreter.take_dirty_rows(), 0);
+
+    // Drawing and erasing a sprite both change rows 4 to 8
+    interpreter.update();
+    assert_eq!(interpreter.take_dirty_rows(), 0b1_1111_0000);
+    interpreter.update();
+    assert_eq!(interpreter.take_dirty_rows(), 0b1_1111_0000);
+
+    // Clearing a blank display changes nothing
+    interpreter.update();
+    assert_eq!(interpreter.take_dirty_rows(), 0);
+    interpreter.video_output[31] = 1;
+    interpreter.update();
+    assert_eq!(interpreter.take_dirty_rows(), 1 << 31);
+}
+
+#[test]
+fn draw_only_redraws_dirty_rows() {
+    let palette = Palette::new(vec![[0; 3], [0xff; 3]]).unwrap();
+    let mut frame = vec![0; 64 * 32 * 4];
+    let mut interpreter = Interpreter::builder().video_output([1; 32]).build();
+    assert!(interpreter.draw(&mut frame, &palette));
+    assert_eq!(frame[..4], [0xff; 4]);
+    assert!(!interpreter.draw(&mut frame, &palette), "nothing changed");
+
+    interpreter.video_output = [0; 32];
+    interpreter.dirty_rows = 1 << 1;
+    assert!(interpreter.draw(&mut frame, &palette));
+    assert_eq!(frame[..4], [0xff; 4], "row 0 was not redrawn");
+    assert_eq!(frame[64 * 4..64 * 4 + 4], [0, 0, 0, 0xff]);
+}
+
+#[test]
+fn display_sprite_wraps_start_position() {
+    let interpreter = run_default(0xd121, |b| {
+        b.registers(&[0, 64 + 2, 32 + 3])
+            .index(0x300)
+            .memory_at(0x300, &[0x80])
+    });
+    assert_eq!(interpreter.video_output[3], 1 << 2);
+}
+
+#[test]
+fn display_sprite_reports_collision() {
+    each_profile(
+        0xd121,
+        |b| {
+            b.registers(&[0, 5, 0])
+                .index(0x300)
+                .memory_at(0x300, &[0x80])
+                .video_output({
+                    let mut video_output = [0; 32];
+                    video_output[0] = 1 << 5;
+                    video_output
+                })
+        },
+        |name, _, i| {
+            assert_eq!(i.video_output[0], 0, "{}", name);
+            assert_eq!(i.registers[0xf], 1, "{}", name);
+        },
+    );
+}
+
+#[test]
+fn display_sprite_clears_stale_collision() {
+    each_profile(
+        0xd121,
+        |b| b.index(0x300).memory_at(0x300, &[0x80]).register(0xf, 1),
+        |name, _, i| assert_eq!(i.registers[0xf], 0, "{}", name),
+    );
+}
+
+#[test]
+fn display_sprite_waits_for_vblank() {
+    for (name, quirks) in profiles().iter() {
+        let mut interpreter = Interpreter::builder()
+            .program(&[0xd125, 0xd125, 0xd125, 0x1206])
+            .index(0x50)
+            .quirks(*quirks)
+            .build();
+        interpreter.update_frame();
+        let drawn = if quirks.vblank { 1 } else { 3 };
+        assert_eq!(interpreter.program_counter, 0x200 + 2 * drawn, "{}", name);
+
+        interpreter.update_frame();
+        interpreter.update_frame();
+        assert_eq!(interpreter.program_counter, 0x206, "{}", name);
+    }
+}
+
+#[test]
+fn display_sprite_out_of_memory_faults() {
+    let mut interpreter = Interpreter::builder()
+        .program(&[0xd12f])
+        .index(0xff8)
+        .build();
+    interpreter.update();
+    assert_eq!(interpreter.fault, Some(Fault::MemoryOutOfBounds(0x1006)));
+}
+
+#[test]
+fn skip_if_key() {
+    each_profile(
+        0xe19e,
+        |b| b.register(1, 0xa).keys(&[0xa]),
+        |name, _, i| assert_eq!(i.program_counter, 0x204, "{}", name),
+    );
+    each_profile(
+        0xe19e,
+        |b| b.register(1, 0xa).keys(&[0xb]),
+        |name, _, i| assert_eq!(i.program_counter, 0x202, "{}", name),
+    );
+}
+
+#[test]
+fn skip_if_not_key() {
+    each_profile(
+        0xe1a1,
+        |b| b.register(1, 0xa).keys(&[0xb]),
+        |name, _, i| assert_eq!(i.program_counter, 0x204, "{}", name),
+    );
+    each_profile(
+        0xe1a1,
+        |b| b.register(1, 0xa).keys(&[0xa]),
+        |name, _, i| assert_eq!(i.program_counter, 0x202, "{}", name),
+    );
+}
+
+#[test]
+fn vx_set_delay_timer() {
+    each_profile(
+        0xf107,
+        |b| b.delay_timer(42),
+        |name, _, i| assert_eq!(i.registers[1], 42, "{}", name),
+    );
+}
+
+#[test]
+fn wait_for_key_stops_the_cpu() {
+    each_profile(
+        0xf10a,
+        |b| b.program(&[0xf10a, 0x6105]),
+        |name, _, i| {
+            assert!(i.waiting_for_key(), "{}", name);
+            assert_eq!(i.program_counter, 0x202, "{}", name);
+        },
+    );
+}
+
+#[test]
+fn wait_for_key_keeps_timers_running() {
+    let mut interpreter = Interpreter::builder()
+        .program(&[0xf10a])
+        .delay_timer(10)
+        .build();
+    interpreter.update_frame();
+    assert!(interpreter.waiting_for_key());
+    assert_eq!(interpreter.delay_timer, 9);
+}
+
+#[test]
+fn wait_for_key_ignores_keys_already_held() {
+    for (name, quirks) in profiles().iter() {
+        let mut interpreter = Interpreter::builder()
+            .program(&[0xf10a])
+            .quirks(*quirks)
+            .keys(&[0x3])
+            .build();
+        interpreter.update();
+        interpreter.update();
+        assert!(interpreter.waiting_for_key(), "{}", name);
+
+        // Releasing and pressing the key again counts
+        interpreter.keypad[0x3] = false;
+        interpreter.update();
+        interpreter.keypad[0x3] = true;
+        interpreter.update();
+        interpreter.keypad[0x3] = false;
+        interpreter.update();
+        assert!(!interpreter.waiting_for_key(), "{}", name);
+        assert_eq!(interpreter.registers[1], 0x3, "{}", name);
+    }
+}
+
+#[test]
+fn wait_for_key_press_or_release() {
+    for (name, quirks) in profiles().iter() {
+        let mut interpreter = Interpreter::builder()
+            .program(&[0xf10a, 0x6205])
+            .quirks(*quirks)
+            .build();
+        interpreter.update();
+        interpreter.keypad[0xc] = true;
+        interpreter.keypad[0xe] = true;
+        interpreter.update();
+        assert_eq!(
+            interpreter.waiting_for_key(),
+            quirks.key_release,
+            "{}",
+            name
+        );
+
+        interpreter.keypad[0xc] = false;
+        interpreter.update();
+        assert!(!interpreter.waiting_for_key(), "{}", name);
+        assert_eq!(interpreter.registers[1], 0xc, "{}", name);
+    }
+}
+
+#[test]
+fn timers_set_vx() {
+    each_profile(
+        0xf115,
+        |b| b.register(1, 42),
+        |name, _, i| assert_eq!(i.delay_timer, 42, "{}", name),
+    );
+    each_profile(
+        0xf118,
+        |b| b.register(1, 42),
+        |name, _, i| assert_eq!(i.sound_timer, 42, "{}", name),
+    );
+}
+
+#[test]
+fn timers_count_down_to_zero() {
+    let mut interpreter = Interpreter::builder().delay_timer(2).sound_timer(1).build();
+    interpreter.update_timers();
+    assert_eq!((interpreter.delay_timer, interpreter.sound_timer), (1, 0));
+    interpreter.update_timers();
+    interpreter.update_timers();
+    assert_eq!((interpreter.delay_timer, interpreter.sound_timer), (0, 0));
+}
+
+#[test]
+fn index_add_vx() {
+    each_profile(
+        0xf11e,
+        |b| b.index(0x100).register(1, 0x20),
+        |name, _, i| assert_eq!(i.index, 0x120, "{}", name),
+    );
+}
+
+#[test]
+fn index_set_font() {
+    each_profile(
+        0xf129,
+        |b| b.register(1, 0xa),
+        |name, _, i| {
+            assert_eq!(i.index, 0x50 + 5 * 0xa, "{}", name);
+            assert_eq!(i.memory.peek(i.index as usize), 0xf0, "{}", name);
+        },
+    );
+}
+
+#[test]
+fn index_set_decimal() {
+    for (value, digits) in [
+        (254u8, [2, 5, 4]),
+        (0, [0, 0, 0]),
+        (7, [0, 0, 7]),
+        (80, [0, 8, 0]),
+    ]
+    .iter()
+    {
+        each_profile(
+            0xf133,
+            |b| b.register(1, *value).index(0x300),
+            |name, _, i| {
+                assert_eq!(i.memory.peek_range(0x300, 3), digits, "{} {}", value, name);
+                assert_eq!(i.index, 0x300, "{} {}", value, name);
+            },
+        );
+    }
+}
+
+#[test]
+fn write_memory() {
+    each_profile(
+        0xf255,
+        |b| b.registers(&[1, 2, 3, 4]).index(0x300),
+        |name, quirks, i| {
+            assert_eq!(i.memory.peek_range(0x300, 4), [1, 2, 3, 0], "{}", name);
+            assert_eq!(i.index, expected_index(quirks, 0x300, 2), "{}", name);
+        },
+    );
+}
+
+#[test]
+fn load_memory() {
+    each_profile(
+        0xf265,
+        |b| b.memory_at(0x300, &[1, 2, 3, 4]).index(0x300),
+        |name, quirks, i| {
+            assert_eq!(&i.registers[..4], &[1, 2, 3, 0], "{}", name);
+            assert_eq!(i.index, expected_index(quirks, 0x300, 2), "{}", name);
+        },
+    );
+}
+
+#[test]
+fn writing_over_executed_code_is_reported() {
+    let mut interpreter = Interpreter::builder()
+        .program(&[0x600f, 0xa200, 0xf055, 0xa300, 0xf055])
+        .build();
+    for _ in 0..5 {
+        interpreter.update();
+    }
+    let code_map = &interpreter.code_map;
+    assert_eq!(
+        code_map.modifications,
+        vec![SelfModification {
+            address: 0x200,
+            program_counter: 0x204,
+            old: 0x60,
+            new: 0x0f,
+        }]
+    );
+    assert!(code_map.is_self_modified(0x200));
+    assert!(code_map.written[0x300] && !code_map.executed[0x300]);
+}
+
+#[test]
+fn rewriting_the_same_code_is_not_reported() {
+    let mut interpreter = Interpreter::builder()
+        .program(&[0x6060, 0xa200, 0xf055])
+        .build();
+    for _ in 0..3 {
+        interpreter.update();
+    }
+    assert!(interpreter.code_map.written[0x200]);
+    assert_eq!(interpreter.code_map.modifications, vec![]);
+}
+
+#[test]
+fn write_memory_below_program_is_flagged() {
+    let interpreter = run_default(0xf155, |b| b.index(0x300));
+    assert_eq!(interpreter.memory.reserved_write(), None);
+
+    let interpreter = run_default(0xf155, |b| b.index(0x1ff).registers(&[0xaa, 0xbb]));
+    assert_eq!(interpreter.memory.reserved_write(), Some(0x1ff));
+    assert_eq!(interpreter.memory.peek_range(0x1ff, 2), [0xaa, 0xbb]);
+}
+
+#[test]
+fn write_protected_memory_ignores_writes() {
+    let mut ram = Ram::default();
+    ram.write_protect = true;
+    let mut interpreter = Interpreter::builder()
+        .memory(Box::new(ram))
+        .program(&[0xf155])
+        .index(0x300)
+        .registers(&[1, 2])
+        .build();
+    interpreter.update();
+    assert_eq!(interpreter.fault, None);
+    assert_eq!(interpreter.memory.peek_range(0x300, 2), [0, 0]);
+}
+
+#[test]
+fn memory_observer_sees_program_accesses() {
+    let accesses = Rc::new(RefCell::new(Vec::new()));
+    let mut ram = Ram::default();
+    let log = accesses.clone();
+    ram.observer = Some(Box::new(move |access, address, value| {
+        log.borrow_mut().push((access, address, value))
+    }));
+    let mut interpreter = Interpreter::builder()
+        .memory(Box::new(ram))
+        .program(&[0xf055])
+        .index(0x300)
+        .register(0, 7)
+        .build();
+    interpreter.update();
+    assert_eq!(
+        *accesses.borrow(),
+        vec![
+            (Access::Fetch, 0x200, 0xf0),
+            (Access::Fetch, 0x201, 0x55),
+            (Access::Write, 0x300, 7),
+        ]
+    );
+}
+
+#[test]
+fn memory_size_follows_platform() {
+    for (platform, fault) in [
+        ("originalChip8", Some(Fault::MemoryOutOfBounds(0x1000))),
+        ("xochip", None),
+    ]
+    .iter()
+    {
+        let mut interpreter = Interpreter::builder()
+            .memory(Box::new(Ram::new(memory::size_for_platform(platform))))
+            .program(&[0xf055])
+            .index(0x1000)
+            .build();
+        interpreter.update();
+        assert_eq!(interpreter.fault, *fault, "{}", platform);
+    }
+}
+
+#[test]
+fn roms_must_fit_the_platforms_memory() {
+    let rom = vec![0x12; 0x2000];
+    assert!(Interpreter::from_bytes(rom.clone(), memory::MEMORY_SIZE).is_err());
+    assert!(RomDatabase::builtin().load(rom.clone()).is_err());
+
+    let json = format!(
+        r#"[{{ "title": "Big", "roms": {{ "{}": {{ "platforms": ["xochip"] }} }} }}]"#,
+        sha1_hex(&rom)
+    );
+    let (interpreter, info) = RomDatabase::from_json(&json).unwrap().load(rom).unwrap();
+    assert_eq!(info.unwrap().title, "Big");
+    assert_eq!(interpreter.memory.len(), memory::XOCHIP_MEMORY_SIZE);
+    assert_eq!(interpreter.memory.peek(0x21ff), 0x12);
+}
+
+#[test]
+fn memory_increment_by_x() {
+    let mut quirks = Quirks::chip8();
+    quirks.memory_increment_by_x = true;
+    let interpreter = run(0xf255, quirks, |b| b.index(0x300));
+    assert_eq!(interpreter.index, 0x302);
+}
+
+#[test]
+fn index_wraps_at_the_end_of_xochip_memory() {
+    let xochip = || {
+        Interpreter::builder()
+            .memory(Box::new(Ram::new(memory::XOCHIP_MEMORY_SIZE)))
+            .quirks(Quirks::xochip())
+    };
+    let mut interpreter = xochip().program(&[0xf055]).index(0xffff).build();
+    interpreter.update();
+    assert_eq!(interpreter.fault, None);
+    assert_eq!(interpreter.index, 0);
+
+    let mut interpreter = xochip()
+        .program(&[0xf01e])
+        .index(0xffff)
+        .register(0, 2)
+        .build();
+    interpreter.update();
+    assert_eq!(interpreter.index, 1);
+}
+
+fn expected_index(quirks: Quirks, index: u16, x: u16) -> u16 {
+    if quirks.memory_leave_i_unchanged {
+        index
+    } else if quirks.memory_increment_by_x {
+        index + x
+    } else {
+        index + x + 1
+    }
+}
+
+#[test]
+fn unknown_opcode_faults() {
+    for opcode in [0x5121, 0x8128, 0xe100, 0xf1ff].iter() {
+        let mut interpreter = Interpreter::builder().program(&[*opcode]).build();
+        interpreter.update();
+        assert_eq!(interpreter.fault, Some(Fault::UnknownOpcode(*opcode)));
+    }
+}
+
+#[test]
+fn program_counter_past_memory_faults() {
+    let mut interpreter = Interpreter::builder().program_counter(0xfff).build();
+    interpreter.update();
+    assert_eq!(
+        interpreter.fault,
+        Some(Fault::ProgramCounterOutOfBounds(0xfff))
+    );
+}
+
+#[test]
+fn program_counter_wraps_at_the_end_of_xochip_memory() {
+    let xochip = || {
+        Interpreter::builder()
+            .memory(Box::new(Ram::new(memory::XOCHIP_MEMORY_SIZE)))
+            .program_counter(0xfffe)
+    };
+    let mut interpreter = xochip().build();
+    interpreter.update();
+    assert_eq!(interpreter.fault, None);
+    assert_eq!(interpreter.program_counter, 0);
+
+    // Skips past the last opcode too
+    let mut interpreter = xochip().memory_at(0xfffe, &[0x30, 0x00]).build();
+    interpreter.update();
+    assert_eq!(interpreter.fault, None);
+    assert_eq!(interpreter.program_counter, 2);
+}
+
+#[test]
+fn instruction_timing_runs_tick_rate_instructions() {
+    let mut interpreter = Interpreter::builder()
+        .program(&[0x7101, 0x1200])
+        .timing(Timing::Instructions(15))
+        .build();
+    interpreter.update_frame();
+    assert_eq!(interpreter.registers[1], 8);
+}
+
+#[test]
+fn vip_timing_charges_by_instruction() {
+    let cost = |opcode: u16, x: u8| {
+        let interpreter = Interpreter::builder()
+            .program(&[opcode])
+            .register(0, x)
+            .build();
+        vip_cycles(&interpreter)
+    };
+    assert_eq!(cost(0x6000, 0), VIP_FETCH_CYCLES + 6);
+    assert!(cost(0x00e0, 0) > cost(0x8014, 0));
+    assert!(cost(0xd00f, 0) > cost(0xd005, 0));
+    assert!(cost(0xd005, 3) > cost(0xd005, 8));
+    assert!(cost(0x3000, 0) > cost(0x3001, 0));
+}
+
+#[test]
+fn vip_timing_runs_fewer_slow_instructions() {
+    let frame = |program: &[u16]| {
+        let mut interpreter = Interpreter::builder()
+            .program(program)
+            .index(0x50)
+            .quirks(Quirks::modern())
+            .timing(Timing::CosmacVip)
+            .build();
+        interpreter.update_frame();
+        interpreter.registers[1]
+    };
+    let adds = frame(&[0x7101, 0x1200]);
+    let draws = frame(&[0x7101, 0xd23f, 0x1200]);
+    assert!(adds > draws, "{} adds, {} with draws", adds, draws);
+}
+
+#[test]
+fn vip_timing_carries_overrun_into_next_frame() {
+    let mut interpreter = Interpreter::builder()
+        .program(&[0x00e0, 0x00e0, 0x00e0, 0x00e0, 0x1208])
+        .timing(Timing::CosmacVip)
+        .build();
+    interpreter.update_frame();
+    assert_eq!(interpreter.program_counter, 0x202);
+    assert!(interpreter.frame_budget < 0);
+    interpreter.update_frame();
+    assert_eq!(interpreter.program_counter, 0x204);
+}
@@ -0,0 +1,102 @@
+//! The on-screen overlay and its font.
+
+use std::time::{Duration, Instant};
+
+use hoyer_chip_8::display::overlay::{
+    draw_text, format_rate, glyph, text_width, Overlay, MESSAGE_DURATION, OVERLAY_HEIGHT,
+    OVERLAY_WIDTH,
+};
+
+/// The alpha of each pixel in a row of an RGBA image `width` pixels wide.
+fn alpha_row(image: &[u8], width: u32, y: u32) -> Vec<u8> {
+    let row = (y * width * 4) as usize;
+    image[row..row + (width * 4) as usize]
+        .chunks(4)
+        .map(|pixel| pixel[3])
+        .collect()
+}
+
+#[test]
+fn font_covers_printable_ascii() {
+    assert_eq!(glyph('A'), [0b010, 0b101, 0b111, 0b101, 0b101]);
+    assert_eq!(glyph('a'), glyph('A'));
+    assert_eq!(glyph('é'), glyph('?'));
+    let digits: Vec<_> = ('0'..='9').map(glyph).collect();
+    for (i, digit) in digits.iter().enumerate() {
+        assert!(
+            !digits[..i].contains(digit),
+            "{} looks like another digit",
+            i
+        );
+    }
+}
+
+#[test]
+fn text_is_drawn_on_a_box() {
+    let width = 16;
+    let mut image = vec![0; (width * 8 * 4) as usize];
+    draw_text(&mut image, width, 1, 0, "T1");
+    assert_eq!(text_width("T1"), 9);
+
+    // A dark box from x = 1 to 9, with the glyphs a pixel in
+    let top = alpha_row(&image, width, 0);
+    assert_eq!(top[0], 0);
+    assert!(top[1..10].iter().all(|alpha| *alpha > 0 && *alpha < 0xff));
+    assert_eq!(top[10], 0);
+    let first_row = alpha_row(&image, width, 1);
+    assert_eq!(first_row[2..5], [0xff, 0xff, 0xff], "the top of T");
+    assert_eq!(first_row[6..9], [0xb0, 0xff, 0xb0], "the top of 1");
+
+    // Text past the edge is cut off
+    draw_text(&mut image, width, 12, 4, "WIDE");
+}
+
+#[test]
+fn rates_are_short() {
+    assert_eq!(format_rate(59.9), "60");
+    assert_eq!(format_rate(1_500.0), "1.5K");
+    assert_eq!(format_rate(42_000.0), "42K");
+    assert_eq!(format_rate(2_240_000.0), "2.2M");
+}
+
+#[test]
+fn stats_are_measured_each_second() {
+    let start = Instant::now();
+    let mut overlay = Overlay::new("Pong", start);
+    overlay.show_stats = true;
+    for frame in 0..=60 {
+        let now = start + Duration::from_millis(frame * 1000 / 60);
+        overlay.count_frames(1, frame * 10, now);
+    }
+    assert_eq!(overlay.fps().round(), 60.0);
+    assert_eq!(overlay.ips().round(), 600.0);
+    assert!(overlay.update(start));
+    assert_eq!(overlay.text(), ["Pong", "60 FPS 600 IPS"]);
+}
+
+#[test]
+fn messages_and_pause_come_and_go() {
+    let start = Instant::now();
+    let mut overlay = Overlay::new("Pong", start);
+    assert!(!overlay.update(start), "nothing to show");
+    assert!(overlay.image().iter().all(|byte| *byte == 0));
+
+    overlay.message("State saved to slot 2", start);
+    overlay.message("Speed 2x", start + Duration::from_secs(1));
+    overlay.set_paused(true);
+    assert!(overlay.update(start + Duration::from_secs(1)));
+    assert_eq!(
+        overlay.text(),
+        ["PAUSED", "State saved to slot 2", "Speed 2x"]
+    );
+    assert!(!overlay.update(start + Duration::from_secs(1)));
+
+    // Messages sit at the bottom, the pause sign in the top right
+    let image = overlay.image();
+    assert!(alpha_row(image, OVERLAY_WIDTH, OVERLAY_HEIGHT - 1)[0] > 0);
+    assert!(alpha_row(image, OVERLAY_WIDTH, 0)[OVERLAY_WIDTH as usize - 1] > 0);
+
+    overlay.set_paused(false);
+    assert!(overlay.update(start + MESSAGE_DURATION));
+    assert_eq!(overlay.text(), ["Speed 2x"]);
+}
@@ -0,0 +1,106 @@
+//! Palettes, themes and drawing the display in colour.
+
+use hoyer_chip_8::database::RomDatabase;
+use hoyer_chip_8::display::palette::{Palette, THEMES};
+use hoyer_chip_8::display::screenshot::{to_ascii, Screenshot};
+use hoyer_chip_8::display::terminal::{render, TextMode};
+use hoyer_chip_8::interpreter::interpreter::Interpreter;
+use hoyer_chip_8::machine::Machine;
+use hoyer_chip_8::settings::Settings;
+
+#[test]
+fn every_theme_colours_both_planes() {
+    for name in THEMES.iter() {
+        let palette = Palette::theme(name).unwrap();
+        assert_eq!(palette.colors().len(), 4, "{}", name);
+        assert_ne!(palette.color(0), palette.color(1), "{}", name);
+        assert_eq!(Palette::parse(&name.to_uppercase()), Ok(palette));
+    }
+}
+
+#[test]
+fn custom_colours_parse() {
+    let palette = Palette::parse("#000000, #ff8000").unwrap();
+    assert_eq!(palette.colors(), &[[0, 0, 0], [0xff, 0x80, 0]]);
+    assert_eq!(
+        palette.color(3),
+        [0xff, 0x80, 0],
+        "missing colours use the last"
+    );
+
+    let sixteen = vec!["#123456"; 16].join(",");
+    assert_eq!(Palette::parse(&sixteen).unwrap().colors().len(), 16);
+    assert!(Palette::parse(&vec!["#123456"; 17].join(",")).is_err());
+    assert!(Palette::parse("#000000").is_err());
+    assert!(Palette::parse("#000000,#12345").is_err());
+    assert!(Palette::parse("sepia").is_err());
+}
+
+#[test]
+fn draw_colours_pixels_by_plane() {
+    let mut interpreter = Interpreter::builder().build();
+    interpreter.video_output[0] = 0b101;
+    interpreter.second_plane[0] = 0b110;
+    let palette = Palette::octo();
+    let mut frame = vec![0; 64 * 32 * 4];
+    interpreter.draw(&mut frame, &palette);
+
+    let pixel = |x: usize| &frame[x * 4..x * 4 + 4];
+    assert_eq!(pixel(0)[..3], palette.color(1));
+    assert_eq!(pixel(1)[..3], palette.color(2));
+    assert_eq!(pixel(2)[..3], palette.color(3));
+    assert_eq!(pixel(3)[..3], palette.background());
+    assert_eq!(pixel(3)[3], 0xff);
+}
+
+#[test]
+fn screenshots_show_both_planes() {
+    let mut interpreter = Interpreter::builder().build();
+    interpreter.video_output[0] = 0b101;
+    interpreter.second_plane[0] = 0b110;
+    let screenshot = Screenshot {
+        scale: 2,
+        palette: Palette::octo(),
+    };
+    let rgba = screenshot.to_rgba(interpreter.planes());
+    let pixel = |x: usize| &rgba[x * 4..x * 4 + 3];
+    assert_eq!(pixel(0), screenshot.palette.color(1));
+    assert_eq!(pixel(2), screenshot.palette.color(2));
+    assert_eq!(pixel(5), screenshot.palette.color(3));
+
+    let mut pbm = Vec::new();
+    screenshot
+        .write_pbm(interpreter.planes(), &mut pbm)
+        .unwrap();
+    assert!(String::from_utf8(pbm).unwrap().contains("\n11111100"));
+    assert!(to_ascii(interpreter.planes()).starts_with("#o@."));
+
+    let planes = Machine::planes(&interpreter);
+    let text = render([&planes[0], &planes[1]], TextMode::HalfBlocks);
+    assert!(text[0].starts_with("▀▀▀ "));
+}
+
+#[test]
+fn settings_override_database_colours() {
+    let json = r##"[{
+        "title": "Colourful",
+        "roms": { "abc": { "colors": { "pixels": ["#000000", "#00ff00"] } } }
+    }]"##;
+    let info = RomDatabase::from_json(json)
+        .unwrap()
+        .lookup_hash("ABC")
+        .unwrap();
+    let rom_palette = info.palette();
+    assert_eq!(rom_palette.as_ref().unwrap().color(1), [0, 0xff, 0]);
+
+    assert_eq!(
+        Settings::default().palette(rom_palette.clone()),
+        rom_palette.clone().unwrap()
+    );
+    let settings = Settings {
+        palette: Some(String::from("amber")),
+        ..Settings::default()
+    };
+    assert_eq!(settings.palette(rom_palette), Palette::amber());
+    assert_eq!(Settings::default().palette(None), Palette::classic());
+}
@@ -0,0 +1,110 @@
+//! Anti-flicker filters.
+
+use hoyer_chip_8::display::palette::Palette;
+use hoyer_chip_8::display::persistence::{Filter, Persistence, FILTERS};
+use hoyer_chip_8::interpreter::interpreter::ALL_ROWS;
+use hoyer_chip_8::settings::Settings;
+
+const BLANK: [u64; 32] = [0; 32];
+
+fn plane(first_row: u64) -> [u64; 32] {
+    let mut plane = BLANK;
+    plane[0] = first_row;
+    plane
+}
+
+#[test]
+fn filters_cycle_through_every_name() {
+    let mut filter = Filter::default();
+    for name in FILTERS.iter() {
+        assert_eq!(filter.name(), *name);
+        assert_eq!(Filter::from_name(name), Some(filter));
+        filter = filter.next();
+    }
+    assert_eq!(filter, Filter::Off);
+
+    let settings: Settings = serde_json::from_str(r#"{ "filter": "stable" }"#).unwrap();
+    assert_eq!(settings.filter, Some(Filter::Stable));
+}
+
+#[test]
+fn blend_halves_flickering_pixels() {
+    let mut persistence = Persistence::new(Filter::Blend);
+    for frame in 0..4 {
+        let row = if frame % 2 == 0 { 0b11 } else { 0b01 };
+        persistence.update([&plane(row), &BLANK]);
+    }
+    assert_eq!(persistence.intensity(0, 0, 0), 1.0);
+    assert_eq!(persistence.intensity(0, 1, 0), 0.5);
+
+    let palette = Palette::new(vec![[0; 3], [200; 3]]).unwrap();
+    let mut frame = vec![0; 64 * 32 * 4];
+    persistence.draw(&mut frame, &palette, ALL_ROWS);
+    assert_eq!(frame[..8], [200, 200, 200, 0xff, 100, 100, 100, 0xff]);
+}
+
+#[test]
+fn only_changed_rows_are_drawn() {
+    let mut persistence = Persistence::new(Filter::Off);
+    let mut planes = BLANK;
+    planes[3] = 1;
+    planes[5] = 1;
+    assert_eq!(persistence.update([&planes, &BLANK]), 0b10_1000);
+    planes[5] = 0;
+    assert_eq!(persistence.update([&planes, &BLANK]), 0b10_0000);
+
+    let palette = Palette::new(vec![[0; 3], [200; 3]]).unwrap();
+    let mut frame = vec![7; 64 * 32 * 4];
+    persistence.draw(&mut frame, &palette, 1 << 3);
+    assert_eq!(frame[3 * 64 * 4..][..4], [200, 200, 200, 0xff]);
+    assert_eq!(frame[5 * 64 * 4..][..4], [7; 4], "row 5 wasn't asked for");
+}
+
+#[test]
+fn decay_fades_pixels_out() {
+    let mut persistence = Persistence::new(Filter::Decay);
+    persistence.decay = 0.5;
+    persistence.update([&plane(1), &plane(1)]);
+    persistence.update([&BLANK, &plane(1)]);
+    assert_eq!(persistence.intensity(0, 0, 0), 0.5);
+    persistence.update([&BLANK, &plane(1)]);
+    assert_eq!(persistence.intensity(0, 0, 0), 0.25);
+    assert_eq!(persistence.intensity(1, 0, 0), 1.0);
+    persistence.update([&plane(1), &BLANK]);
+    assert_eq!(persistence.intensity(0, 0, 0), 1.0, "pixels light at once");
+}
+
+#[test]
+fn stable_holds_erased_sprites_briefly() {
+    let mut persistence = Persistence::new(Filter::Stable);
+    persistence.update([&plane(0b0110), &BLANK]);
+    // Erased, then redrawn one pixel over
+    persistence.update([&BLANK, &BLANK]);
+    assert_eq!(persistence.intensity(0, 1, 0), 1.0);
+    persistence.update([&plane(0b1100), &BLANK]);
+    assert_eq!(persistence.intensity(0, 1, 0), 0.0);
+    assert_eq!(persistence.intensity(0, 3, 0), 1.0);
+
+    // Pixels that stay erased go out eventually
+    for _ in 0..4 {
+        persistence.update([&BLANK, &BLANK]);
+    }
+    assert_eq!(persistence.intensity(0, 3, 0), 0.0);
+}
+
+#[test]
+fn settles_once_the_picture_stops_changing() {
+    let mut persistence = Persistence::new(Filter::Decay);
+    persistence.decay = 0.5;
+    assert_eq!(persistence.update([&plane(1), &BLANK]), 1);
+    assert_eq!(persistence.update([&BLANK, &BLANK]), 1, "still fading");
+    assert!(!persistence.is_settled());
+    while persistence.update([&BLANK, &BLANK]) != 0 {}
+    assert!(persistence.is_settled());
+    assert_eq!(persistence.intensity(0, 0, 0), 0.0);
+
+    persistence.set_filter(Filter::Off);
+    assert!(!persistence.is_settled());
+    assert_eq!(persistence.update([&BLANK, &BLANK]), 0);
+    assert!(persistence.is_settled());
+}
@@ -0,0 +1,95 @@
+//! Resetting the interpreter and reloading a ROM when it changes on disk.
+
+use std::env::temp_dir;
+use std::fs::{create_dir_all, remove_dir_all, write};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use hoyer_chip_8::interpreter::interpreter::Interpreter;
+use hoyer_chip_8::interpreter::quirks::Quirks;
+use hoyer_chip_8::interpreter::timing::Timing;
+use hoyer_chip_8::watch::RomWatcher;
+
+/// Sets V0 to 5, draws the font's 0, stores V0 at 0x300 and loops.
+const PROGRAM: [u16; 6] = [0x6005, 0xa050, 0xd015, 0xa300, 0xf055, 0x120a];
+
+#[test]
+fn reset_starts_the_rom_again_with_the_same_settings() {
+    let mut interpreter = Interpreter::builder().program(&PROGRAM).build();
+    interpreter.quirks = Quirks::schip();
+    interpreter.timing = Timing::Instructions(30);
+    interpreter.delay_timer = 40;
+    for _ in 0..6 {
+        interpreter.update();
+    }
+    assert_eq!(interpreter.registers[0], 5);
+    assert_eq!(interpreter.memory.peek(0x300), 5);
+    assert_ne!(interpreter.video_output, [0; 32]);
+
+    interpreter.take_dirty_rows();
+    interpreter.reset();
+    assert_eq!(interpreter.program_counter, 0x200);
+    assert_eq!(interpreter.registers, [0; 16]);
+    assert_eq!(interpreter.index, 0);
+    assert_eq!(interpreter.delay_timer, 0);
+    assert_eq!(interpreter.memory.peek(0x300), 0);
+    assert_eq!(interpreter.memory.peek(0x200), 0x60);
+    assert_eq!(interpreter.memory.peek(0x50), 0xf0, "font is back");
+    assert_eq!(interpreter.video_output, [0; 32]);
+    assert_ne!(interpreter.take_dirty_rows(), 0);
+    assert_eq!(interpreter.quirks, Quirks::schip());
+    assert_eq!(interpreter.timing, Timing::Instructions(30));
+
+    // It runs the same way again
+    for _ in 0..6 {
+        interpreter.update();
+    }
+    assert_eq!(interpreter.memory.peek(0x300), 5);
+}
+
+#[test]
+fn reset_forgets_writes_below_the_program() {
+    let mut interpreter = Interpreter::builder().program(&[0xa1ff, 0xf055]).build();
+    interpreter.update();
+    interpreter.update();
+    assert_eq!(interpreter.memory.reserved_write(), Some(0x1ff));
+
+    interpreter.reset();
+    assert_eq!(interpreter.memory.reserved_write(), None);
+}
+
+#[test]
+fn reload_runs_the_new_rom() {
+    let mut interpreter = Interpreter::builder().program(&PROGRAM).build();
+    interpreter.update();
+    interpreter.reload(vec![0x60, 0x09, 0x12, 0x02]).unwrap();
+    assert_eq!(interpreter.rom, [0x60, 0x09, 0x12, 0x02]);
+    assert_eq!(interpreter.memory.peek(0x204), 0, "old ROM is cleared");
+    interpreter.update();
+    assert_eq!(interpreter.registers[0], 9);
+
+    // A ROM that outgrew the memory leaves the old one running
+    assert!(interpreter.reload(vec![0x12; 0x1000]).is_err());
+    assert_eq!(interpreter.rom, [0x60, 0x09, 0x12, 0x02]);
+    assert_eq!(interpreter.registers[0], 9);
+}
+
+#[test]
+fn watcher_sees_the_rom_rewritten() {
+    let dir = temp_dir().join(format!("chip8-watch-{}", std::process::id()));
+    create_dir_all(&dir).unwrap();
+    let rom = dir.join("GAME.ch8");
+    write(&rom, [0x12, 0x00]).unwrap();
+    let watcher = RomWatcher::new(&rom).unwrap();
+    assert_eq!(watcher.path(), rom.canonicalize().unwrap());
+
+    // Other files in the directory don't count
+    write(dir.join("NOTES.txt"), "notes").unwrap();
+    write(&rom, [0x60, 0x01, 0x12, 0x02]).unwrap();
+    let start = Instant::now();
+    while !watcher.changed() {
+        assert!(start.elapsed() < Duration::from_secs(5), "no change seen");
+        sleep(Duration::from_millis(20));
+    }
+    remove_dir_all(&dir).unwrap();
+}
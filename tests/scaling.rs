@@ -0,0 +1,54 @@
+//! Fitting the picture to the window.
+
+use hoyer_chip_8::display::scaling::{Scaling, Viewport};
+use hoyer_chip_8::settings::Settings;
+
+#[test]
+fn integer_scaling_letterboxes() {
+    assert_eq!(
+        Scaling::Integer.viewport((64, 32), (700, 700)),
+        Viewport {
+            x: 30.0,
+            y: 190.0,
+            width: 640.0,
+            height: 320.0
+        }
+    );
+    // Pillarboxed in a wide window
+    assert_eq!(
+        Scaling::Integer.viewport((64, 32), (1920, 320)),
+        Viewport {
+            x: 640.0,
+            y: 0.0,
+            width: 640.0,
+            height: 320.0
+        }
+    );
+}
+
+#[test]
+fn fractional_scaling_fills_one_side() {
+    let viewport = Scaling::Fractional.viewport((64, 32), (700, 700));
+    assert_eq!((viewport.width, viewport.height), (700.0, 350.0));
+    assert_eq!((viewport.x, viewport.y), (0.0, 175.0));
+
+    let hires = Scaling::Fractional.viewport((128, 64), (1000, 1000));
+    assert_eq!(hires.width / hires.height, 2.0);
+    assert_eq!(
+        Scaling::Integer.viewport((128, 64), (1000, 1000)).width,
+        896.0
+    );
+}
+
+#[test]
+fn pictures_never_shrink_below_one_pixel() {
+    let viewport = Scaling::Integer.viewport((64, 32), (40, 20));
+    assert_eq!((viewport.width, viewport.height), (64.0, 32.0));
+    assert_eq!((viewport.x, viewport.y), (0.0, 0.0));
+
+    let settings: Settings =
+        serde_json::from_str(r#"{ "scaling": "fractional", "fullscreen": true }"#).unwrap();
+    assert_eq!(settings.scaling, Scaling::Fractional);
+    assert!(settings.fullscreen);
+    assert_eq!(Settings::default().scaling, Scaling::Integer);
+}
@@ -0,0 +1,276 @@
+//! The CDP1802 core on hand-assembled programs, and the VIP board around it
+//! with a stand-in monitor ROM.
+
+use std::env;
+use std::fs::read;
+
+use hoyer_chip_8::headless::Runner;
+use hoyer_chip_8::interpreter::quirks::Quirks;
+use hoyer_chip_8::interpreter::timing::Timing;
+use hoyer_chip_8::vip::cdp1802::{Bus, Cdp1802};
+use hoyer_chip_8::vip::{Vip, VipError, IMAGE_SIZE};
+
+/// Flat 64K of memory with nothing on the ports.
+struct TestBus {
+    memory: Vec<u8>,
+    flags: [bool; 4],
+    output: Vec<(u8, u8)>,
+}
+
+impl TestBus {
+    fn new(program: &[u8]) -> TestBus {
+        let mut memory = vec![0; 0x10000];
+        memory[..program.len()].copy_from_slice(program);
+        TestBus {
+            memory,
+            flags: [false; 4],
+            output: Vec::new(),
+        }
+    }
+}
+
+impl Bus for TestBus {
+    fn read(&mut self, address: u16) -> u8 {
+        self.memory[address as usize]
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.memory[address as usize] = value;
+    }
+
+    fn output(&mut self, port: u8, value: u8) {
+        self.output.push((port, value));
+    }
+
+    fn input(&mut self, port: u8) -> u8 {
+        0x10 + port
+    }
+
+    fn flag(&self, flag: u8) -> bool {
+        self.flags[flag as usize - 1]
+    }
+}
+
+/// Run `steps` instructions from 0000, returning the total machine cycles.
+fn run(cpu: &mut Cdp1802, bus: &mut TestBus, steps: usize) -> u32 {
+    (0..steps).map(|_| cpu.step(bus)).sum()
+}
+
+#[test]
+fn add_and_subtract_set_df() {
+    // LDI FF, ADI 02, then LDI 01, SMI 02, then LDI 05, SMI 02
+    let mut bus = TestBus::new(&[0xf8, 0xff, 0xfc, 0x02, 0xf8, 0x01, 0xff, 0x02]);
+    let mut cpu = Cdp1802::default();
+    run(&mut cpu, &mut bus, 2);
+    assert_eq!((cpu.d, cpu.df), (0x01, true));
+    run(&mut cpu, &mut bus, 2);
+    assert_eq!((cpu.d, cpu.df), (0xff, false), "DF clear means borrow");
+
+    let mut bus = TestBus::new(&[0xf8, 0x05, 0xff, 0x02, 0x7f, 0x01]);
+    let mut cpu = Cdp1802::default();
+    run(&mut cpu, &mut bus, 2);
+    assert_eq!((cpu.d, cpu.df), (0x03, true));
+    run(&mut cpu, &mut bus, 1);
+    assert_eq!(
+        (cpu.d, cpu.df),
+        (0x02, true),
+        "SMBI borrows only without DF"
+    );
+}
+
+#[test]
+fn shifts_through_df() {
+    // LDI 81, SHR, SHRC, SHLC
+    let mut bus = TestBus::new(&[0xf8, 0x81, 0xf6, 0x76, 0x7e]);
+    let mut cpu = Cdp1802::default();
+    run(&mut cpu, &mut bus, 2);
+    assert_eq!((cpu.d, cpu.df), (0x40, true));
+    run(&mut cpu, &mut bus, 1);
+    assert_eq!((cpu.d, cpu.df), (0xa0, false));
+    run(&mut cpu, &mut bus, 1);
+    assert_eq!((cpu.d, cpu.df), (0x40, true));
+}
+
+#[test]
+fn short_branches_stay_in_page() {
+    // LDI 00, BZ 10
+    let mut bus = TestBus::new(&[0xf8, 0x00, 0x32, 0x10]);
+    let mut cpu = Cdp1802::default();
+    run(&mut cpu, &mut bus, 2);
+    assert_eq!(cpu.registers[0], 0x0010);
+
+    // BN1 not taken skips the target byte, B4 taken
+    let mut bus = TestBus::new(&[0x3c, 0x10, 0x37, 0x20]);
+    bus.flags[0] = true;
+    bus.flags[3] = true;
+    let mut cpu = Cdp1802::default();
+    run(&mut cpu, &mut bus, 1);
+    assert_eq!(cpu.registers[0], 0x0002);
+    run(&mut cpu, &mut bus, 1);
+    assert_eq!(cpu.registers[0], 0x0020);
+}
+
+#[test]
+fn long_branches_and_skips_take_three_cycles() {
+    // LBR 1234
+    let mut bus = TestBus::new(&[0xc0, 0x12, 0x34]);
+    let mut cpu = Cdp1802::default();
+    assert_eq!(run(&mut cpu, &mut bus, 1), 3);
+    assert_eq!(cpu.registers[0], 0x1234);
+
+    // SEQ, LSQ over two bytes, LSNQ not taken
+    let mut bus = TestBus::new(&[0x7b, 0xcd, 0x00, 0x00, 0xc5]);
+    let mut cpu = Cdp1802::default();
+    assert_eq!(run(&mut cpu, &mut bus, 3), 2 + 3 + 3);
+    assert_eq!(cpu.registers[0], 0x0005);
+}
+
+#[test]
+fn mark_and_return() {
+    // R2 = 0080, SEX 3, MARK, then RET through X = 2
+    let mut bus = TestBus::new(&[0xf8, 0x80, 0xa2, 0xe3, 0x79]);
+    let mut cpu = Cdp1802::default();
+    run(&mut cpu, &mut bus, 4);
+    assert_eq!(bus.memory[0x80], 0x30);
+    assert_eq!((cpu.x, cpu.registers[2]), (0, 0x007f));
+
+    cpu.x = 2;
+    cpu.registers[2] = 0x80;
+    bus.memory[cpu.registers[0] as usize] = 0x70;
+    run(&mut cpu, &mut bus, 1);
+    assert_eq!((cpu.x, cpu.p, cpu.registers[2]), (3, 0, 0x0081));
+    assert!(cpu.interrupt_enable);
+}
+
+#[test]
+fn interrupts_save_x_and_p() {
+    let mut cpu = Cdp1802 {
+        x: 5,
+        p: 3,
+        interrupt_enable: true,
+        ..Cdp1802::default()
+    };
+    assert_eq!(cpu.interrupt(), 1);
+    assert_eq!(
+        (cpu.t, cpu.x, cpu.p, cpu.interrupt_enable),
+        (0x53, 2, 1, false)
+    );
+    assert_eq!(cpu.interrupt(), 0, "interrupts are disabled until RET");
+}
+
+#[test]
+fn input_and_output_use_rx() {
+    // R2 = 0080, SEX 2, OUT 4, INP 6
+    let mut bus = TestBus::new(&[0xf8, 0x80, 0xa2, 0xe2, 0x64, 0x6e]);
+    bus.memory[0x80] = 0x42;
+    let mut cpu = Cdp1802::default();
+    run(&mut cpu, &mut bus, 5);
+    assert_eq!(bus.output, vec![(4, 0x42)]);
+    assert_eq!(bus.memory[0x81], 0x16);
+    assert_eq!(cpu.d, 0x16);
+}
+
+#[rustfmt::skip]
+/// A stand-in for the monitor ROM that just jumps to the program at 0000.
+const MONITOR: [u8; 15] = [
+    0xc0, 0x80, 0x03, // LBR 8003, so RAM appears at 0000
+    0xf8, 0x0a, 0xa3, // R3 = 800A
+    0xf8, 0x80, 0xb3, //
+    0xd3, // SEP 3
+    0xf8, 0x00, 0xa0, 0xb0, // R0 = 0000
+    0xd0, // SEP 0
+];
+
+#[rustfmt::skip]
+/// Turns the display on with an interrupt routine that shows memory from 0300.
+const DISPLAY_PROGRAM: [u8; 45] = [
+    0xf8, 0x00, 0xb3, 0xf8, 0x07, 0xa3, // R3 = 0007, as R0 is for DMA
+    0xd3, // SEP 3
+    0xf8, 0x00, 0xb2, 0xf8, 0xff, 0xa2, // R2 = 00FF
+    0xf8, 0x00, 0xb1, 0xf8, 0x21, 0xa1, // R1 = 0021
+    0xe2, // SEX 2
+    0x69, // INP 1
+    0x30, 0x15, // BR 15
+    0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0x70, // RET
+    0x22, 0x78, // DEC 2, SAV
+    0xf8, 0x03, 0xb0, 0xf8, 0x00, 0xa0, // R0 = 0300
+    0x34, 0x29, // B1 29, until the picture starts
+    0x30, 0x20, // BR 20
+];
+
+#[test]
+fn vip_displays_memory_by_dma() {
+    let mut vip = Vip::new(&MONITOR, &DISPLAY_PROGRAM).unwrap();
+    // Nothing repeats lines like the CHIP-8 interpreter does, so the last row
+    // shown is line 124
+    let mut program = vec![0; 0x500];
+    program[0x300 - 0x200] = 0x80;
+    program[0x300 + 124 * 8 + 7 - 0x200] = 0x01;
+    vip.load_bytes(program).unwrap();
+    for _ in 0..3 {
+        vip.update_frame();
+    }
+    let video_output = vip.video_output();
+    assert_eq!(video_output[0], 1);
+    assert_eq!(video_output[31], 1 << 63);
+}
+
+#[test]
+fn vip_reports_latched_key_on_ef3() {
+    #[rustfmt::skip]
+    let program = [
+        0xf8, 0x00, 0xb2, 0xf8, 0xf0, 0xa2, // R2 = 00F0
+        0xf8, 0x05, 0x52, // M(00F0) = 5
+        0xe2, 0x62, // SEX 2, OUT 2
+        0x36, 0x11, // B3 11
+        0xf8, 0x00, 0x30, 0x13, // D = 0
+        0xf8, 0x01, // D = 1
+        0x52, // STR 2
+        0x30, 0x14, // BR 14
+    ];
+    for held in [false, true].iter() {
+        let mut vip = Vip::new(&MONITOR, &program).unwrap();
+        vip.keypad()[5] = *held;
+        vip.update_frame();
+        assert_eq!(vip.memory()[0xf1], *held as u8);
+    }
+}
+
+#[test]
+fn vip_rejects_oversized_images() {
+    let image = [0; IMAGE_SIZE + 1];
+    assert_eq!(
+        Vip::new(&image, &[]).err(),
+        Some(VipError::MonitorTooLarge(IMAGE_SIZE + 1))
+    );
+    assert_eq!(
+        Vip::new(&[], &image).err(),
+        Some(VipError::InterpreterTooLarge(IMAGE_SIZE + 1))
+    );
+}
+
+/// Compare the interpreter with the real thing, given the VIP's monitor ROM and
+/// CHIP-8 interpreter in `CHIP8_VIP_MONITOR` and `CHIP8_VIP_INTERPRETER`.
+#[test]
+fn interpreter_matches_vip() {
+    let (monitor, interpreter) = match (
+        env::var("CHIP8_VIP_MONITOR"),
+        env::var("CHIP8_VIP_INTERPRETER"),
+    ) {
+        (Ok(monitor), Ok(interpreter)) => (read(monitor).unwrap(), read(interpreter).unwrap()),
+        _ => return,
+    };
+    let rom = "roms/test/test_opcode.c8";
+    let mut vip = Vip::new(&monitor, &interpreter).unwrap();
+    vip.load_bytes(read(rom).unwrap()).unwrap();
+    let mut runner = Runner::from_rom(rom).unwrap();
+    runner.interpreter.quirks = Quirks::chip8();
+    runner.interpreter.timing = Timing::CosmacVip;
+
+    for _ in 0..300 {
+        vip.update_frame();
+    }
+    runner.run(300);
+    assert_eq!(vip.video_output(), runner.interpreter.video_output);
+}